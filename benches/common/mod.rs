@@ -0,0 +1,41 @@
+//! temporary config for the benchmarks
+
+use poke_escape_server::Config;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// TempDir is a directory for one benchmark, removed with its content on drop
+pub struct TempDir {
+    /// path of the directory
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// creates an empty directory in the temporary directory of the system
+    pub fn new(name: &str) -> io::Result<Self> {
+        let path =
+            std::env::temp_dir().join(format!("poke_escape_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path); // left over by a killed run
+        fs::create_dir_all(&path)?;
+        Ok(TempDir { path })
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// returns a config serving `random` maps, written into `dir`
+pub fn config(dir: &TempDir) -> io::Result<Config> {
+    let file = dir.path.join("config.toml");
+    fs::write(
+        &file,
+        "[Maps]\nversion = \"0.2.0\"\n\n[map.random]\nversion = \"0.1.0\"\nformat = \"random\"\n",
+    )?;
+    let mut config = Config::new();
+    config.config = file.to_string_lossy().to_string();
+    Ok(config)
+}
@@ -0,0 +1,250 @@
+//! throughput of the room events sent by the coordinator
+//!
+//! Run with `cargo bench --bench rooms`. Clients are simulated by sinks
+//! counting the lines written to them, optionally waiting on every write
+//! like a slow network connection. Every workload runs against the rooms of
+//! `server_client` and against one thread writing every event, the design
+//! before the rooms got their own threads.
+//!
+//! The events of a room are written by one thread at a time, also while a
+//! room is stopped and started again. With slow clients a run can not be
+//! faster than `2 * ROOMS` rooms writing one event per delay each, the
+//! single thread not faster than one event per delay. Without a delay the
+//! single thread is faster, it saves handing the events to the rooms.
+mod common;
+
+use common::TempDir;
+use poke_escape_server::map::store::MapStore;
+use poke_escape_server::server::request::{self, Query};
+use poke_escape_server::server::transport::{Connection, Transport, Writer};
+use poke_escape_server::server::{server_client, Ident, Message, MessageBody};
+use std::collections::HashMap;
+use std::io;
+use std::io::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+/// number of rooms
+const ROOMS: usize = 8;
+
+/// clients in every room
+const MEMBERS: usize = 8;
+
+/// times every client leaves its room and comes back
+const ROUNDS: usize = 10;
+
+/// Sink counts the lines written to a client
+#[derive(Clone)]
+struct Sink {
+    /// lines written to every sink
+    lines: Arc<AtomicUsize>,
+
+    /// time every write takes
+    delay: Duration,
+}
+
+impl Read for Sink {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Ok(0)
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.delay.is_zero() {
+            std::thread::sleep(self.delay);
+        }
+        let lines = buf.iter().filter(|b| **b == b'\n').count();
+        self.lines.fetch_add(lines, Ordering::SeqCst);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Sink {
+    fn try_clone(&self) -> io::Result<Connection> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn peer(&self) -> String {
+        "sink".to_string()
+    }
+}
+
+/// Design is the coordinator a run sends its messages to
+#[derive(Clone, Copy, Debug)]
+enum Design {
+    /// `server_client`, every room on its own thread
+    Rooms,
+
+    /// one thread keeping every room and writing every event, see `single`
+    Single,
+}
+
+/// events sent by the workload
+///
+/// Joining a room with `n` members sends `n` events, leaving it sends one to
+/// every member left. Moving all clients of a room to another room one by
+/// one sends `MEMBERS * (MEMBERS - 1)` events.
+fn expected_events() -> usize {
+    let moving = MEMBERS * (MEMBERS - 1);
+    ROOMS * (moving / 2 + ROUNDS * 2 * moving)
+}
+
+/// returns the rooms joined by the clients, in order
+///
+/// Every client joins its room, then in every round the clients of a room
+/// move to another room one by one and back again.
+fn workload(ids: &[Vec<String>]) -> Vec<(String, String)> {
+    let mut moves = Vec::new();
+    for (room, members) in ids.iter().enumerate() {
+        for id in members {
+            moves.push((id.clone(), format!("room-{}", room)));
+        }
+    }
+    for _ in 0..ROUNDS {
+        for (room, members) in ids.iter().enumerate() {
+            for id in members {
+                moves.push((id.clone(), format!("away-{}", room)));
+            }
+            for id in members {
+                moves.push((id.clone(), format!("room-{}", room)));
+            }
+        }
+    }
+    moves
+}
+
+/// the coordinator of `Design::Single`
+///
+/// Like `server_client` before the rooms got their own threads, one thread
+/// keeps the members of every room and writes every event itself.
+fn single(rx: mpsc::Receiver<(String, String)>, writers: HashMap<String, Writer>) {
+    let mut rooms: HashMap<String, Vec<String>> = HashMap::new();
+    let mut joined: HashMap<String, String> = HashMap::new();
+    let notify = |members: &[String], line: &str| {
+        for member in members {
+            writers[member].notice(line).unwrap();
+        }
+    };
+    for (id, room) in rx {
+        if let Some(old) = joined.insert(id.clone(), room.clone()) {
+            if old == room {
+                continue; // already in the room
+            }
+            let members = rooms.get_mut(&old).unwrap();
+            members.retain(|member| member != &id);
+            notify(members, &format!("event leave {} {}", id, old));
+        }
+        let members = rooms.entry(room.clone()).or_default();
+        notify(members, &format!("event join {} {}", id, room));
+        members.push(id);
+    }
+}
+
+/// runs the workload, returns the events per second
+fn run(maps: &Arc<MapStore>, design: Design, delay: Duration) -> f64 {
+    let lines = Arc::new(AtomicUsize::new(0));
+    let ids: Vec<Vec<String>> = (0..ROOMS)
+        .map(|room| (0..MEMBERS).map(|n| format!("c{}-{}", room, n)).collect())
+        .collect();
+    let writers: Vec<(String, Writer)> = ids
+        .iter()
+        .flatten()
+        .map(|id| {
+            let sink = Sink {
+                lines: Arc::clone(&lines),
+                delay,
+            };
+            // like a client speaking protocol 0.2.0, older clients get no events
+            let writer = Writer::new(Box::new(sink));
+            writer.accept_notices();
+            (id.clone(), writer)
+        })
+        .collect();
+    let moves = workload(&ids);
+
+    let start;
+    match design {
+        Design::Rooms => {
+            let (tx, rx) = mpsc::channel();
+            server_client(rx, 0, Arc::clone(maps));
+            let send =
+                |id: &str, body: MessageBody| tx.send(Message::new(id.to_string(), body)).unwrap();
+            for (id, writer) in writers {
+                send(
+                    &id,
+                    MessageBody::IDENTIFY(Ident::new(id.clone()).with_writer(writer)),
+                );
+            }
+            start = Instant::now();
+            for (id, room) in moves {
+                send(&id, MessageBody::AttachToGroup(room));
+            }
+            wait_for_events(&lines, start);
+
+            // the coordinator still answers
+            request::ask(&tx, "bench", Query::Stats, Duration::from_secs(5)).unwrap();
+        }
+        Design::Single => {
+            let (tx, rx) = mpsc::channel();
+            let writers = writers.into_iter().collect();
+            std::thread::spawn(move || single(rx, writers));
+            start = Instant::now();
+            for message in moves {
+                tx.send(message).unwrap();
+            }
+            wait_for_events(&lines, start);
+        }
+    }
+    expected_events() as f64 / start.elapsed().as_secs_f64()
+}
+
+/// waits until the sinks got every event of the workload
+fn wait_for_events(lines: &AtomicUsize, start: Instant) {
+    let expected = expected_events();
+    while lines.load(Ordering::SeqCst) < expected {
+        if start.elapsed() > Duration::from_secs(120) {
+            panic!(
+                "only {} of {} events sent",
+                lines.load(Ordering::SeqCst),
+                expected
+            );
+        }
+        std::thread::sleep(Duration::from_micros(200));
+    }
+    assert_eq!(lines.load(Ordering::SeqCst), expected);
+}
+
+fn main() {
+    let dir = TempDir::new("bench").unwrap();
+    let config = common::config(&dir).unwrap();
+    let maps = Arc::new(MapStore::new(&config.config, false).unwrap());
+
+    println!(
+        "{} rooms, {} clients each, {} rounds, {} events per run",
+        ROOMS,
+        MEMBERS,
+        ROUNDS,
+        expected_events()
+    );
+    for (name, delay) in [
+        ("fast clients", Duration::ZERO),
+        ("slow clients (100us per write)", Duration::from_micros(100)),
+    ] {
+        for design in [Design::Single, Design::Rooms] {
+            let runs: Vec<f64> = (0..3).map(|_| run(&maps, design, delay)).collect();
+            let best = runs.iter().cloned().fold(0.0, f64::max);
+            println!(
+                "{:<32} {:<8} {:>10.0} events/s (best of 3)",
+                name,
+                format!("{:?}", design),
+                best
+            );
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use super::super::error::ErrorKind;
+use super::super::server::listener::{ListenAddr, Stream};
+use super::Client;
+use colored::*;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// LoadSettings describes the simulated clients of a load test
+///
+/// Chat messages are sent to every other member of the room, room switches
+/// send a join and a leave event to every member of both rooms.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadSettings {
+    /// number of simulated clients
+    pub clients: usize,
+
+    /// time every client runs after connecting
+    pub duration: Duration,
+
+    /// number of rooms the clients are spread over
+    pub rooms: usize,
+
+    /// maps loaded per second by every client, 0 loads no maps
+    pub map_rate: f64,
+
+    /// chat messages per second by every client, 0 sends none
+    pub chat_rate: f64,
+
+    /// room switches per second by every client, 0 stays in the first room
+    pub switch_rate: f64,
+
+    /// names of the maps to load, used in turn
+    pub maps: Vec<String>,
+
+    /// time to wait for the server to answer, also while connecting
+    pub timeout: Duration,
+}
+
+impl Default for LoadSettings {
+    fn default() -> Self {
+        LoadSettings {
+            clients: 10,
+            duration: Duration::from_secs(10),
+            rooms: 4,
+            map_rate: 1.0,
+            chat_rate: 1.0,
+            switch_rate: 0.5,
+            maps: vec!["random".to_string()],
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Latencies collects the durations of one kind of request
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Latencies {
+    /// measured durations, in the order they were measured
+    p_samples: Vec<Duration>,
+}
+
+impl Latencies {
+    /// creates an empty collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a measured duration
+    pub fn add(&mut self, latency: Duration) {
+        self.p_samples.push(latency);
+    }
+
+    /// adds every duration of another collection
+    pub fn merge(&mut self, other: Latencies) {
+        self.p_samples.extend(other.p_samples);
+    }
+
+    /// returns the number of measured durations
+    pub fn len(&self) -> usize {
+        self.p_samples.len()
+    }
+
+    /// returns true if nothing was measured
+    pub fn is_empty(&self) -> bool {
+        self.p_samples.is_empty()
+    }
+
+    /// returns the duration `percent` percent of the requests were faster than
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::client::loadtest::Latencies;
+    /// use std::time::Duration;
+    /// let mut latencies = Latencies::new();
+    /// for ms in 1..=100 {
+    ///     latencies.add(Duration::from_millis(ms));
+    /// }
+    /// assert_eq!(latencies.percentile(50.0), Some(Duration::from_millis(50)));
+    /// assert_eq!(latencies.percentile(100.0), Some(Duration::from_millis(100)));
+    /// ```
+    pub fn percentile(&self, percent: f64) -> Option<Duration> {
+        if self.p_samples.is_empty() {
+            return None;
+        }
+        let mut samples = self.p_samples.clone();
+        samples.sort();
+        let rank = (percent / 100.0 * samples.len() as f64).ceil() as usize;
+        Some(samples[rank.clamp(1, samples.len()) - 1])
+    }
+}
+
+impl fmt::Display for Latencies {
+    /// prints p50, p90, p99 and the maximum
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ms = |percent| match self.percentile(percent) {
+            Some(latency) => format!("{:.1}ms", latency.as_secs_f64() * 1000.0),
+            None => "-".to_string(),
+        };
+        write!(
+            f,
+            "p50 {}, p90 {}, p99 {}, max {}",
+            ms(50.0),
+            ms(90.0),
+            ms(99.0),
+            ms(100.0)
+        )
+    }
+}
+
+/// LoadTestReport holds the results of a load test
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoadTestReport {
+    /// number of simulated clients
+    pub clients: usize,
+
+    /// clients which finished the handshake
+    pub connected: usize,
+
+    /// time to connect and finish the handshake
+    pub connect: Latencies,
+
+    /// time to load a map
+    pub maps: Latencies,
+
+    /// time for a chat message to be accepted
+    pub chats: Latencies,
+
+    /// number of room switches
+    pub room_switches: usize,
+
+    /// number of events the clients received
+    pub events: usize,
+
+    /// errors by description
+    pub errors: BTreeMap<String, usize>,
+
+    /// time the whole test took
+    pub elapsed: Duration,
+}
+
+impl LoadTestReport {
+    /// returns the number of errors
+    pub fn error_count(&self) -> usize {
+        self.errors.values().sum()
+    }
+
+    /// counts an error
+    fn error(&mut self, error: String) {
+        *self.errors.entry(error).or_insert(0) += 1;
+    }
+
+    /// adds the results of another client
+    fn merge(&mut self, other: LoadTestReport) {
+        self.clients += other.clients;
+        self.connected += other.connected;
+        self.connect.merge(other.connect);
+        self.maps.merge(other.maps);
+        self.chats.merge(other.chats);
+        self.room_switches += other.room_switches;
+        self.events += other.events;
+        for (error, count) in other.errors {
+            *self.errors.entry(error).or_insert(0) += count;
+        }
+    }
+}
+
+impl fmt::Display for LoadTestReport {
+    /// prints the results for the terminal
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        writeln!(
+            f,
+            "{} of {} clients connected in {:.1}s",
+            self.connected.to_string().green(),
+            self.clients,
+            secs
+        )?;
+        writeln!(f, "  connect: {}", self.connect)?;
+        writeln!(
+            f,
+            "  maps:    {} loaded, {:.1}/s, {}",
+            self.maps.len().to_string().yellow(),
+            self.maps.len() as f64 / secs,
+            self.maps
+        )?;
+        writeln!(
+            f,
+            "  chat:    {} sent, {:.1}/s, {}",
+            self.chats.len().to_string().yellow(),
+            self.chats.len() as f64 / secs,
+            self.chats
+        )?;
+        writeln!(
+            f,
+            "  rooms:   {} switches, {} events received",
+            self.room_switches.to_string().yellow(),
+            self.events.to_string().yellow()
+        )?;
+        if self.errors.is_empty() {
+            return writeln!(f, "  errors:  {}", "none".green());
+        }
+        writeln!(f, "  errors:  {}", self.error_count().to_string().red())?;
+        for (error, count) in &self.errors {
+            writeln!(f, "    {:>5} {}", count, error.red())?;
+        }
+        Ok(())
+    }
+}
+
+/// runs the simulated clients against the server at the address
+///
+/// Every client connects, identifies as `load-<n>`, joins a room and then
+/// loads maps, chats and switches rooms at the configured rates until the
+/// duration passed. The actions of the clients are spread over the interval, so they
+/// do not all hit the server at once.
+pub fn run(addr: &ListenAddr, settings: &LoadSettings) -> LoadTestReport {
+    let start = Instant::now();
+    let workers: Vec<_> = (0..settings.clients)
+        .map(|n| {
+            let addr = addr.clone();
+            let settings = settings.clone();
+            thread::spawn(move || simulate(n, &addr, &settings))
+        })
+        .collect();
+
+    let mut report = LoadTestReport::default();
+    for worker in workers {
+        match worker.join() {
+            Ok(client) => report.merge(client),
+            Err(_) => {
+                report.clients += 1;
+                report.error("client thread panicked".to_string());
+            }
+        }
+    }
+    report.elapsed = start.elapsed();
+    report
+}
+
+/// runs one simulated client, returns its results
+fn simulate(n: usize, addr: &ListenAddr, settings: &LoadSettings) -> LoadTestReport {
+    let mut report = LoadTestReport {
+        clients: 1,
+        ..LoadTestReport::default()
+    };
+    if let Err(err) = session(n, addr, settings, &mut report) {
+        report.error(err.to_string());
+    }
+    report
+}
+
+/// the actions of one simulated client
+fn session(
+    n: usize,
+    addr: &ListenAddr,
+    settings: &LoadSettings,
+    report: &mut LoadTestReport,
+) -> Result<()> {
+    let connecting = Instant::now();
+    let stream = Stream::connect(addr)?;
+    stream.set_read_timeout(Some(settings.timeout))?;
+    stream.set_write_timeout(Some(settings.timeout))?;
+    let mut client = Client::new(Box::new(stream))?;
+    client.set_timeout(Some(settings.timeout))?;
+    report.connect.add(connecting.elapsed());
+    report.connected += 1;
+
+    let rooms = settings.rooms.max(1);
+    let mut room = n % rooms;
+    client.identify(&format!("load-{}", n))?;
+    client.join(&format!("room-{}", room))?;
+
+    // spread the first action of the clients over one interval
+    let offset = (n as f64 + 1.0) / (settings.clients as f64 + 1.0);
+    let start = Instant::now();
+    let deadline = start + settings.duration;
+    let mut next_map = if settings.maps.is_empty() {
+        None
+    } else {
+        next(start, settings.map_rate, offset)
+    };
+    let mut next_chat = next(start, settings.chat_rate, offset);
+    let mut next_switch = next(start, settings.switch_rate, offset);
+    let mut loaded = 0;
+    let mut chatted = 0;
+    loop {
+        let due = [next_map, next_chat, next_switch, Some(deadline)]
+            .iter()
+            .flatten()
+            .min()
+            .copied()
+            .unwrap_or(deadline);
+        if due >= deadline {
+            wait(&mut client, deadline, report)?;
+            break;
+        }
+        wait(&mut client, due, report)?;
+
+        if next_map.is_some_and(|at| at <= due) {
+            let name = &settings.maps[loaded % settings.maps.len()];
+            loaded += 1;
+            let loading = Instant::now();
+            match client.map(name) {
+                Ok(_) => report.maps.add(loading.elapsed()),
+                // the server answered, the connection can still be used
+                Err(err) if matches!(err.kind(), ErrorKind::ServerError(_)) => {
+                    report.error(format!("map {}: {}", name, err))
+                }
+                Err(err) => return Err(err),
+            }
+            next_map = next_map.and_then(|at| next(at, settings.map_rate, 1.0));
+        }
+        if next_chat.is_some_and(|at| at <= due) {
+            chatted += 1;
+            let sending = Instant::now();
+            client.chat(&format!("message {} of load-{}", chatted, n))?;
+            report.chats.add(sending.elapsed());
+            next_chat = next_chat.and_then(|at| next(at, settings.chat_rate, 1.0));
+        }
+        if next_switch.is_some_and(|at| at <= due) {
+            room = (room + 1) % rooms;
+            client.join(&format!("room-{}", room))?;
+            report.room_switches += 1;
+            next_switch = next_switch.and_then(|at| next(at, settings.switch_rate, 1.0));
+        }
+        report.events += client.take_events().len();
+    }
+    client.quit()
+}
+
+/// counts the events arriving until the given time
+///
+/// Reading the events keeps the server from blocking on a full connection.
+fn wait(client: &mut Client, until: Instant, report: &mut LoadTestReport) -> Result<()> {
+    loop {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(());
+        }
+        match client.next_event(Some(left))? {
+            Some(_) => report.events += 1,
+            None => return Ok(()),
+        }
+    }
+}
+
+/// returns the time of the next action at `rate` per second, `None` for rate 0
+///
+/// A rate too small to ever happen returns `None` as well.
+fn next(last: Instant, rate: f64, fraction: f64) -> Option<Instant> {
+    if rate <= 0.0 || rate.is_nan() {
+        return None;
+    }
+    let interval = Duration::try_from_secs_f64(fraction / rate).ok()?;
+    last.checked_add(interval)
+}
@@ -0,0 +1,322 @@
+use super::error::{Error, ErrorKind};
+use super::map::Map;
+use super::server::listener::{ListenAddr, Stream};
+use super::server::transport::Connection;
+use semver::Version;
+use std::collections::VecDeque;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::time::Duration;
+
+#[doc(inline)]
+pub use super::error::Result;
+
+/// interactive debug client
+pub mod repl;
+
+/// simulated clients measuring the load a server handles
+pub mod loadtest;
+
+// tests as sub module
+#[cfg(test)] // only add when running tests
+mod test;
+
+/// version of the protocol the client sends in the handshake
+///
+/// Events are sent to clients of protocol 0.2.0 or newer only.
+pub const PROTOCOL_VERSION: &str = "0.2.0";
+
+/// ServerEvent is a notice the server sends without being asked
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerEvent {
+    /// a client joined the room of this client
+    Joined {
+        /// name of the room
+        room: String,
+
+        /// id of the client which joined
+        id: String,
+    },
+
+    /// a client left the room of this client
+    Left {
+        /// name of the room
+        room: String,
+
+        /// id of the client which left
+        id: String,
+    },
+
+    /// a client of the room of this client sent a chat message
+    Chat {
+        /// id of the client which sent the message
+        id: String,
+
+        /// the message
+        text: String,
+    },
+
+    /// the server stops, the client has the given time to quit
+    Shutdown(Duration),
+}
+
+impl ServerEvent {
+    /// parses a line sent by the server, returns `None` for other lines
+    ///
+    /// The room name or chat message is the rest of an event line, it may
+    /// contain spaces.
+    fn parse(line: &str) -> Option<Self> {
+        let mut words = line.splitn(4, ' ');
+        match (words.next()?, words.next()?) {
+            ("event", kind) => {
+                let id = words.next()?.to_string();
+                let rest = words.next().filter(|rest| !rest.is_empty())?.to_string();
+                match kind {
+                    "join" => Some(ServerEvent::Joined { room: rest, id }),
+                    "leave" => Some(ServerEvent::Left { room: rest, id }),
+                    "chat" => Some(ServerEvent::Chat { id, text: rest }),
+                    _ => None,
+                }
+            }
+            ("shutdown", secs) => Some(ServerEvent::Shutdown(Duration::from_secs(
+                secs.parse().ok()?,
+            ))),
+            _ => None,
+        }
+    }
+}
+
+/// Client is a blocking connection to a PokeEscape server
+///
+/// Events sent by the server while the client waits for an answer are kept
+/// and returned by `next_event`.
+///
+/// # Example
+/// ```
+/// use poke_escape_server::client::Client;
+/// use poke_escape_server::server::listener::ListenAddr;
+/// use poke_escape_server::Config;
+/// let mut config = Config::new();
+/// config.listen = vec![ListenAddr::Tcp("127.0.0.1:0".to_string())];
+/// let server = config.start().unwrap();
+///
+/// let mut client = Client::connect(&server.local_addrs()[0]).unwrap();
+/// client.identify("ash").unwrap();
+/// client.join("pallet").unwrap();
+/// client.quit().unwrap();
+/// server.shutdown().unwrap();
+/// ```
+pub struct Client {
+    /// connection the commands are written to
+    p_writer: Connection,
+
+    /// connection the answers are read from
+    p_reader: BufReader<Connection>,
+
+    /// version the server sent in the handshake
+    p_server_version: Version,
+
+    /// events read while waiting for an answer
+    p_events: VecDeque<ServerEvent>,
+
+    /// time to wait for an answer, `None` waits forever
+    p_timeout: Option<Duration>,
+
+    /// start of a line cut by a read timeout, completed by the next read
+    p_partial: Vec<u8>,
+}
+
+impl Client {
+    /// connects to the address and negotiates the protocol
+    pub fn connect(addr: &ListenAddr) -> Result<Self> {
+        Self::new(Box::new(Stream::connect(addr)?))
+    }
+
+    /// negotiates the protocol over an open connection
+    ///
+    /// A server not speaking the protocol version of the client answers the
+    /// first command with `Protocol mismatch.`, which is returned as
+    /// `VersionNotSupported` by the commands waiting for an answer.
+    pub fn new(connection: Connection) -> Result<Self> {
+        let mut client = Client {
+            p_reader: BufReader::new(connection.try_clone()?),
+            p_writer: connection,
+            p_server_version: Version::new(0, 0, 0),
+            p_events: VecDeque::new(),
+            p_timeout: None,
+            p_partial: Vec::new(),
+        };
+        client.send(&format!("POKE-ESCAPE_{}", PROTOCOL_VERSION))?;
+        let line = client.read_line()?;
+        let version = match line.strip_prefix("POKE-ESCAPE-SERVER_") {
+            Some(version) => Version::parse(version)?,
+            None => return Err(Error::new(ErrorKind::ServerError(line))),
+        };
+        client.p_server_version = version;
+        Ok(client)
+    }
+
+    /// returns the version the server sent in the handshake
+    pub fn server_version(&self) -> &Version {
+        &self.p_server_version
+    }
+
+    /// sets the time to wait for an answer, `None` waits forever
+    ///
+    /// A command not answered in time returns an io error.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.p_writer.set_read_timeout(timeout)?;
+        self.p_timeout = timeout;
+        Ok(())
+    }
+
+    /// identifies the client, needed before joining a room
+    ///
+    /// # Errors
+    /// Returns `ServerError` if the server refused the id, because the client
+    /// is identified already or the id contains whitespace.
+    pub fn identify(&mut self, id: &str) -> Result<()> {
+        self.send(&format!("identify {}", id))?;
+        self.accepted()
+    }
+
+    /// joins a room, leaving the room joined before
+    ///
+    /// # Errors
+    /// Returns `ServerError` if the server refused the room, because the
+    /// client is not identified or the name is empty.
+    pub fn join(&mut self, room: &str) -> Result<()> {
+        self.send(&format!("join {}", room))?;
+        self.accepted()
+    }
+
+    /// sends a chat message to the other members of the room
+    ///
+    /// # Errors
+    /// Returns `IoInvalidInput` for a message with a line break and
+    /// `ServerError` if the server refused the message, because the client
+    /// is in no room or the message is empty.
+    pub fn chat(&mut self, text: &str) -> Result<()> {
+        if text.contains(['\r', '\n']) {
+            return Err(Error::new(ErrorKind::IoInvalidInput));
+        }
+        self.send(&format!("chat {}", text))?;
+        self.accepted()
+    }
+
+    /// loads a map from the server
+    ///
+    /// # Errors
+    /// Returns `ServerError` if the server could not load the map.
+    pub fn map(&mut self, name: &str) -> Result<Map> {
+        self.send(&format!("map {}", name))?;
+        let line = self.answer()?;
+        match line.strip_prefix("map ") {
+            Some(json) => Map::from_json(json),
+            None => Err(Error::new(ErrorKind::ServerError(line))),
+        }
+    }
+
+    /// leaves the server and closes the connection
+    pub fn quit(mut self) -> Result<()> {
+        self.send("quit")?;
+        let line = self.answer()?;
+        if line != "Bye" {
+            return Err(Error::new(ErrorKind::ServerError(line)));
+        }
+        let _ = self.p_writer.shutdown();
+        Ok(())
+    }
+
+    /// returns the next event sent by the server
+    ///
+    /// Waits up to `timeout` for an event, `None` waits forever. Returns
+    /// `None` if no event arrived in time.
+    pub fn next_event(&mut self, timeout: Option<Duration>) -> Result<Option<ServerEvent>> {
+        if let Some(event) = self.p_events.pop_front() {
+            return Ok(Some(event));
+        }
+        self.p_writer.set_read_timeout(timeout)?;
+        let line = self.read_line();
+        self.p_writer.set_read_timeout(self.p_timeout)?;
+        let line = match line {
+            Ok(line) => line,
+            Err(err) if is_timeout(&err) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        match ServerEvent::parse(&line) {
+            Some(event) => Ok(Some(event)),
+            None => Err(Error::new(ErrorKind::ServerError(line))),
+        }
+    }
+
+    /// returns the events read while waiting for answers
+    ///
+    /// Unlike `next_event` this does not wait for the server.
+    pub fn take_events(&mut self) -> Vec<ServerEvent> {
+        self.p_events.drain(..).collect()
+    }
+
+    /// returns the connection and the reader of the answers
+    ///
+    /// Used by tools reading the answers on another thread, like the debug
+    /// client of `repl`. Events kept by the client are dropped.
+    pub fn into_parts(self) -> (Connection, BufReader<Connection>) {
+        (self.p_writer, self.p_reader)
+    }
+
+    /// writes a command to the server
+    fn send(&mut self, command: &str) -> Result<()> {
+        self.p_writer
+            .write_all(format!("{}\n", command).as_bytes())?;
+        self.p_writer.flush()?;
+        Ok(())
+    }
+
+    /// reads the answer of a command, keeping the events read before it
+    fn answer(&mut self) -> Result<String> {
+        loop {
+            let line = self.read_line()?;
+            if line == "Protocol mismatch." {
+                return Err(Error::new(ErrorKind::VersionNotSupported(format!(
+                    "client {}, server {}",
+                    PROTOCOL_VERSION, self.p_server_version
+                ))));
+            }
+            match ServerEvent::parse(&line) {
+                Some(event) => self.p_events.push_back(event),
+                None => return Ok(line),
+            }
+        }
+    }
+
+    /// reads the answer of a command answered with `OK` on success
+    fn accepted(&mut self) -> Result<()> {
+        let line = self.answer()?;
+        if line != "OK" {
+            return Err(Error::new(ErrorKind::ServerError(line)));
+        }
+        Ok(())
+    }
+
+    /// reads one line without the line break
+    ///
+    /// The bytes read before a timeout are kept, so a line cut by a short
+    /// timeout of `next_event` is returned whole by the next read.
+    fn read_line(&mut self) -> io::Result<String> {
+        if self.p_reader.read_until(b'\n', &mut self.p_partial)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = String::from_utf8_lossy(&self.p_partial)
+            .trim_end()
+            .to_string();
+        self.p_partial.clear();
+        Ok(line)
+    }
+}
+
+/// returns true if the error is a read timeout
+fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::TimedOut || err.kind() == io::ErrorKind::WouldBlock
+}
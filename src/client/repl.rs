@@ -0,0 +1,197 @@
+use super::super::map::{render, Map};
+use super::super::server::listener::ListenAddr;
+use super::Client;
+use colored::*;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, ExternalPrinter, Helper};
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// commands of the protocol, completed with tab
+pub const COMMANDS: [&str; 5] = ["chat", "identify", "join", "map", "quit"];
+
+/// prompt of the debug client
+pub const PROMPT: &str = "poke> ";
+
+/// time to wait for `Bye` after sending `quit`
+const QUIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// CommandHelper completes the commands of the protocol
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CommandHelper;
+
+impl Completer for CommandHelper {
+    type Candidate = String;
+
+    /// completes the command at the start of the line
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok((0, complete(&line[..pos])))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {}
+
+impl Helper for CommandHelper {}
+
+/// returns the commands starting with the typed word
+///
+/// Only the first word of a line is a command, arguments are not completed.
+///
+/// # Example
+/// ```
+/// use poke_escape_server::client::repl::complete;
+/// assert_eq!(complete("id"), vec!["identify ".to_string()]);
+/// assert!(complete("map ran").is_empty());
+/// ```
+pub fn complete(typed: &str) -> Vec<String> {
+    if typed.contains(char::is_whitespace) {
+        return Vec::new();
+    }
+    COMMANDS
+        .iter()
+        .filter(|command| command.starts_with(typed))
+        .map(|command| match *command {
+            "quit" => command.to_string(),
+            command => format!("{} ", command),
+        })
+        .collect()
+}
+
+/// formats a line sent by the server for the terminal
+///
+/// Maps are drawn as grid, other json is pretty-printed, events and errors
+/// are colored.
+pub fn pretty(line: &str) -> String {
+    let (word, rest) = match line.find(' ') {
+        Some(space) => (&line[..space], line[space + 1..].trim()),
+        None => (line, ""),
+    };
+    match word {
+        "map" => match Map::from_json(rest) {
+            Ok(map) => render::terminal(&map).trim_end().to_string(),
+            Err(_) => format!("{} {}", word.green(), json(rest)),
+        },
+        "event" => format!("{} {}", word.cyan(), rest),
+        "shutdown" => format!(
+            "{} server stops, {}s left to quit",
+            word.yellow(),
+            rest.yellow()
+        ),
+        "error" | "Error" | "Unknown" | "Protocol" => line.red().to_string(),
+        "Bye" => line.green().to_string(),
+        _ if rest.starts_with('{') || rest.starts_with('[') => {
+            format!("{} {}", word.green(), json(rest))
+        }
+        _ => line.to_string(),
+    }
+}
+
+/// pretty-prints json, or returns the text as it is if it is no json
+fn json(text: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(text)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| text.to_string())
+}
+
+/// connects to the server and reads commands until `quit` or end of input
+///
+/// Every line of the server is printed with `pretty` as soon as it arrives,
+/// also while a command is typed. The commands are kept in `history` if a
+/// file is given.
+pub fn run(addr: &ListenAddr, history: Option<PathBuf>) -> Result<()> {
+    let client = Client::connect(addr)?;
+    println!(
+        "connected to {} (server {}), {} for completion, {} to leave",
+        addr.to_string().green(),
+        client.server_version().to_string().yellow(),
+        "tab".blue(),
+        "quit".blue()
+    );
+    let (mut writer, mut reader) = client.into_parts();
+
+    let mut editor: Editor<CommandHelper, rustyline::history::DefaultHistory> =
+        Editor::new().map_err(|err| err.to_string())?;
+    editor.set_helper(Some(CommandHelper));
+    if let Some(history) = &history {
+        let _ = editor.load_history(history); // missing on the first run
+    }
+
+    // without a terminal the lines are printed directly
+    let mut printer = editor.create_external_printer().ok();
+    let (closed_tx, closed) = mpsc::channel();
+    thread::spawn(move || {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let out = pretty(line.trim_end());
+                    let printed = match &mut printer {
+                        Some(printer) => printer.print(format!("{}\n", out)).is_ok(),
+                        None => false,
+                    };
+                    if !printed {
+                        println!("{}", out);
+                    }
+                }
+            }
+        }
+        let _ = closed_tx.send(());
+    });
+
+    loop {
+        if closed.try_recv().is_ok() {
+            println!("{}", "connection closed by the server".yellow());
+            break;
+        }
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+                writer.write_all(b"quit\n")?;
+                let _ = closed.recv_timeout(QUIT_TIMEOUT);
+                break;
+            }
+            Err(err) => return Err(err.to_string().into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        writer.write_all(format!("{}\n", line).as_bytes())?;
+        writer.flush()?;
+        if line == "quit" {
+            let _ = closed.recv_timeout(QUIT_TIMEOUT);
+            break;
+        }
+    }
+
+    if let Some(history) = &history {
+        if let Err(err) = editor.save_history(history) {
+            eprintln!("could not save history: {}", err);
+        }
+    }
+    Ok(())
+}
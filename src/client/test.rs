@@ -0,0 +1,250 @@
+use super::*;
+use crate::map::store::MapStore;
+use crate::server::handle::ServerHandle;
+use crate::server::transport::pipe;
+use crate::testing::{config, TempDir};
+use std::sync::Arc;
+
+#[test]
+fn parse_events() {
+    assert_eq!(
+        ServerEvent::parse("event join ash hall"),
+        Some(ServerEvent::Joined {
+            room: "hall".to_string(),
+            id: "ash".to_string()
+        })
+    );
+    assert_eq!(
+        ServerEvent::parse("event leave ash great hall"),
+        Some(ServerEvent::Left {
+            room: "great hall".to_string(),
+            id: "ash".to_string()
+        })
+    );
+    assert_eq!(
+        ServerEvent::parse("event chat ash hello  there"),
+        Some(ServerEvent::Chat {
+            id: "ash".to_string(),
+            text: "hello  there".to_string()
+        })
+    );
+    assert_eq!(
+        ServerEvent::parse("shutdown 10"),
+        Some(ServerEvent::Shutdown(Duration::from_secs(10)))
+    );
+    assert_eq!(ServerEvent::parse("event join ash"), None);
+    assert_eq!(ServerEvent::parse("event join ash "), None);
+    assert_eq!(ServerEvent::parse("Bye"), None);
+}
+
+#[test]
+fn rooms_and_maps() {
+    let dir = TempDir::new("client_rooms").unwrap();
+    let server = config(&dir).unwrap().start().unwrap();
+    let addr = server.local_addrs()[0].clone();
+
+    let mut ash = Client::connect(&addr).unwrap();
+    assert_eq!(ash.server_version(), &Version::new(0, 1, 0));
+    ash.identify("ash").unwrap();
+    ash.join("hall").unwrap();
+    // the answer is sent after the commands before it were handled
+    ash.map("random").unwrap();
+
+    let mut misty = Client::connect(&addr).unwrap();
+    misty.identify("misty").unwrap();
+    misty.join("hall").unwrap();
+    let timeout = Some(Duration::from_secs(5));
+    assert_eq!(
+        ash.next_event(timeout).unwrap(),
+        Some(ServerEvent::Joined {
+            room: "hall".to_string(),
+            id: "misty".to_string()
+        })
+    );
+
+    let map = misty.map("random 3").unwrap();
+    assert!(map.name().starts_with("random"));
+    assert!(!map.rows().is_empty());
+    match misty.map("nope") {
+        Err(err) => assert_eq!(
+            err.kind(),
+            ErrorKind::ServerError("error could not load map".to_string())
+        ),
+        Ok(_) => panic!("loaded a map which does not exist"),
+    }
+
+    misty.quit().unwrap();
+    assert_eq!(
+        ash.next_event(timeout).unwrap(),
+        Some(ServerEvent::Left {
+            room: "hall".to_string(),
+            id: "misty".to_string()
+        })
+    );
+    assert_eq!(
+        ash.next_event(Some(Duration::from_millis(50))).unwrap(),
+        None
+    );
+
+    let stopping = std::thread::spawn(move || server.shutdown());
+    assert_eq!(
+        ash.next_event(timeout).unwrap(),
+        Some(ServerEvent::Shutdown(Duration::from_secs(5)))
+    );
+    ash.quit().unwrap();
+    stopping.join().unwrap().unwrap();
+}
+
+#[test]
+fn refused_commands() {
+    let dir = TempDir::new("client_refused").unwrap();
+    let server = config(&dir).unwrap().start().unwrap();
+    let refused = ErrorKind::ServerError("Error".to_string());
+
+    let mut client = Client::connect(&server.local_addrs()[0]).unwrap();
+    assert_eq!(client.join("hall").unwrap_err().kind(), refused);
+    assert_eq!(client.identify("ash ketchum").unwrap_err().kind(), refused);
+    client.identify("ash").unwrap();
+    assert_eq!(client.identify("misty").unwrap_err().kind(), refused);
+    assert_eq!(client.chat("hello").unwrap_err().kind(), refused);
+    client.join("great hall").unwrap();
+    assert_eq!(client.chat("").unwrap_err().kind(), refused);
+    assert_eq!(
+        client.chat("hello\nquit").unwrap_err().kind(),
+        ErrorKind::IoInvalidInput
+    );
+    client.chat("hello").unwrap();
+
+    // every answer was read, the next one belongs to the next command
+    assert!(client.map("random").is_ok());
+    client.quit().unwrap();
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn over_pipe() {
+    let dir = TempDir::new("client_pipe").unwrap();
+    let config = config(&dir).unwrap();
+    let maps = Arc::new(MapStore::new(&config.config, false).unwrap());
+    let server = ServerHandle::start_on(&config, maps, &[]).unwrap();
+    let (client, session) = pipe("client");
+    server.serve(Box::new(session)).unwrap();
+
+    let mut client = Client::new(Box::new(client)).unwrap();
+    client.identify("brock").unwrap();
+    assert!(client.map("random").unwrap().name().starts_with("random"));
+    client.quit().unwrap();
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn protocol_mismatch() {
+    let (client, mut server) = pipe("mismatch");
+    server
+        .write_all(b"POKE-ESCAPE-SERVER_0.0.1\nProtocol mismatch.\n")
+        .unwrap();
+    let mut client = Client::new(Box::new(client)).unwrap();
+    assert_eq!(client.server_version(), &Version::new(0, 0, 1));
+    match client.map("random") {
+        Err(err) => assert_eq!(
+            err.kind(),
+            ErrorKind::VersionNotSupported("client 0.2.0, server 0.0.1".to_string())
+        ),
+        Ok(_) => panic!("loaded a map without a protocol"),
+    }
+}
+
+#[test]
+fn line_cut_by_timeout() {
+    let (client, mut server) = pipe("partial");
+    server.write_all(b"POKE-ESCAPE-SERVER_0.1.0\n").unwrap();
+    let mut client = Client::new(Box::new(client)).unwrap();
+
+    server.write_all(b"event join ash ha").unwrap();
+    let timeout = Some(Duration::from_millis(10));
+    assert_eq!(client.next_event(timeout).unwrap(), None);
+    server.write_all(b"ll\n").unwrap();
+    assert_eq!(
+        client.next_event(timeout).unwrap(),
+        Some(ServerEvent::Joined {
+            room: "hall".to_string(),
+            id: "ash".to_string()
+        })
+    );
+}
+
+mod repl {
+    use crate::client::repl::{complete, pretty};
+
+    #[test]
+    fn complete_commands() {
+        assert_eq!(
+            complete(""),
+            vec!["chat ", "identify ", "join ", "map ", "quit"]
+        );
+        assert_eq!(complete("q"), vec!["quit"]);
+        assert!(complete("x").is_empty());
+        assert!(complete("join ha").is_empty());
+    }
+
+    #[test]
+    fn pretty_lines() {
+        colored::control::set_override(false);
+        let map = r#"map {"p_name": "a", "p_features": null, "p_map": [[1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]]}"#;
+        assert!(pretty(map).starts_with("a (28x1), features: none\n████"));
+        assert_eq!(pretty(r#"map {"a": 1}"#), "map {\n  \"a\": 1\n}");
+        assert_eq!(pretty("event join ash hall"), "event join ash hall");
+        assert_eq!(
+            pretty("shutdown 10"),
+            "shutdown server stops, 10s left to quit"
+        );
+        assert_eq!(pretty("Unknown command"), "Unknown command");
+    }
+}
+
+mod loadtest {
+    use crate::client::loadtest::{run, Latencies, LoadSettings};
+    use crate::testing::{config, TempDir};
+    use std::time::Duration;
+
+    #[test]
+    fn percentiles() {
+        let mut latencies = Latencies::new();
+        assert_eq!(latencies.percentile(50.0), None);
+        latencies.add(Duration::from_millis(3));
+        latencies.add(Duration::from_millis(1));
+        latencies.add(Duration::from_millis(2));
+        assert_eq!(latencies.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(latencies.percentile(50.0), Some(Duration::from_millis(2)));
+        assert_eq!(latencies.percentile(99.0), Some(Duration::from_millis(3)));
+    }
+
+    #[test]
+    fn local_server() {
+        let dir = TempDir::new("client_loadtest").unwrap();
+        let server = config(&dir).unwrap().start().unwrap();
+        let settings = LoadSettings {
+            clients: 3,
+            duration: Duration::from_millis(500),
+            rooms: 2,
+            map_rate: 10.0,
+            chat_rate: 10.0,
+            switch_rate: 10.0,
+            maps: vec!["random".to_string(), "nope".to_string()],
+            timeout: Duration::from_secs(5),
+        };
+        let report = run(&server.local_addrs()[0], &settings);
+        server.shutdown().unwrap();
+
+        assert_eq!(report.clients, 3);
+        assert_eq!(report.connected, 3);
+        assert_eq!(report.connect.len(), 3);
+        assert!(!report.maps.is_empty());
+        assert!(!report.chats.is_empty());
+        assert!(report.room_switches > 0);
+        assert!(report.events > 0);
+        // every second map does not exist
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.error_count() > 0);
+    }
+}
@@ -64,7 +64,7 @@ impl Error {
 impl fmt::Display for Error {
     /// standart formater for print! macro
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Error of Kind {}", self.my_kind.to_string()) // TODO: create a more readable output
+        write!(f, "Error of Kind {}", self.my_kind) // TODO: create a more readable output
     }
 }
 
@@ -72,7 +72,7 @@ impl fmt::Display for Error {
 impl fmt::Debug for Error {
     /// formater for `Debug` in print! macro
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "(Error of Kind {})", self.my_kind.to_string())
+        write!(f, "(Error of Kind {})", self.my_kind)
     }
 }
 
@@ -122,9 +122,7 @@ impl std::convert::From<serde_json::error::Error> for Error {
 /// implement std::convert::from for semver::SemVerError
 impl std::convert::From<semver::SemVerError> for Error {
     fn from(err: semver::SemVerError) -> Self {
-        let error = match err {
-            semver::SemVerError::ParseError(error) => error,
-        };
+        let semver::SemVerError::ParseError(error) = err;
         Error {
             my_kind: ErrorKind::VersionNotParsable(error),
         }
@@ -215,6 +213,10 @@ pub enum ErrorKind {
     /// Format Not Supported, raised when the format defined in config.toml is not supported
     FormatNotSupported,
 
+    /// Map Not Valid error, raised when the strict map loader rejects a map
+    /// holds the name of the map
+    MapNotValid(String),
+
     /// Field Not Exists, raised when a important field is missing in config
     /// also raised if file is of the wrong type
     FieldNotExists(String), // TODO: create better type for data
@@ -228,6 +230,14 @@ pub enum ErrorKind {
     /// Version Not Parsable error, used if the version cannot be parsed
     VersionNotParsable(String),
 
+    /// Version Not Supported error, raised when no loader supports the version of config.toml
+    /// holds the version and the supported ranges
+    VersionNotSupported(String),
+
+    /// Server Error is returned by the client when the server answered with an error
+    /// holds the answer of the server
+    ServerError(String),
+
     /// Pool To Small is returned when the Threapool is to small to be created
     PoolToSmall,
 
@@ -272,10 +282,13 @@ impl ErrorKind {
             ErrorKind::IoOther => String::from("IoOther"),
             ErrorKind::IoUnexpectedEof => String::from("IoUnexpectedEof"),
             ErrorKind::FormatNotSupported => String::from("FormatNotSupported"),
+            ErrorKind::MapNotValid(data) => format!("MapNotValid({})", data),
             ErrorKind::FieldNotExists(data) => format!("FieldNotExists({})", data),
             ErrorKind::NotParsable(data) => format!("NotParsable({})", data),
             ErrorKind::NoVersionSupplied => String::from("NoVersionSupplied"),
             ErrorKind::VersionNotParsable(data) => format!("VersionNotParsable({})", data),
+            ErrorKind::VersionNotSupported(data) => format!("VersionNotSupported({})", data),
+            ErrorKind::ServerError(data) => format!("ServerError({})", data),
             ErrorKind::PoolToSmall => String::from("PoolToSmall"),
             ErrorKind::PoolSendError(t) => match t {
                 true => String::from("PoolSendError(Job)"),
@@ -283,7 +296,7 @@ impl ErrorKind {
             },
             ErrorKind::Other(data) => format!("Other({})", data),
             ErrorKind::Unknown(data) => format!("Unknown({})", data),
-            _ => format!("Not covered??!!!\n"),
+            _ => String::from("Not covered??!!!\n"),
         }
     }
 
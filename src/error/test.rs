@@ -229,6 +229,12 @@ mod error_kind_error_string {
         assert_eq!(kind.error_string(), String::from("FormatNotSupported"));
     }
 
+    #[test]
+    fn map_not_valid() {
+        let kind = ErrorKind::MapNotValid("test".to_string());
+        assert_eq!(kind.error_string(), String::from("MapNotValid(test)"));
+    }
+
     #[test]
     fn field_not_exists() {
         let kind = ErrorKind::FieldNotExists("test".to_string());
@@ -256,6 +262,21 @@ mod error_kind_error_string {
         );
     }
 
+    #[test]
+    fn version_not_supported() {
+        let kind = ErrorKind::VersionNotSupported("test".to_string());
+        assert_eq!(
+            kind.error_string(),
+            String::from("VersionNotSupported(test)")
+        );
+    }
+
+    #[test]
+    fn server_error() {
+        let kind = ErrorKind::ServerError("test".to_string());
+        assert_eq!(kind.error_string(), String::from("ServerError(test)"));
+    }
+
     #[test]
     fn pool_to_small() {
         let kind = ErrorKind::PoolToSmall;
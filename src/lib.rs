@@ -4,9 +4,11 @@
 //! with the client written in GreenFoot (java)
 #![deny(missing_docs)]
 use colored::*;
-use std::net::TcpListener;
+use server::handle::ServerHandle;
+use server::listener::ListenAddr;
 use std::process;
-use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// general tcp module for talking with the client and negotiating the
 /// protocoll to use
@@ -21,7 +23,21 @@ pub mod error;
 /// module providing map loader
 pub mod map;
 
+/// settings of the server read from config.toml and the environment
+pub mod settings;
+
+/// typed client for the PokeEscape protocol
+pub mod client;
+
+// temporary directories and configs shared by the unit tests
+#[cfg(test)] // only add when running tests
+mod testing;
+
+/// time between two checks of the config file with `Config::watch`
+pub const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// struct deriving cli parsing. It also implements the run function, serving the main function
+#[derive(Clone, Debug)]
 pub struct Config {
     /// configures the port to listen on
     pub port: u16,
@@ -29,6 +45,9 @@ pub struct Config {
     /// configures the interface (ip) to listen on
     pub host: String,
 
+    /// addresses to listen on, `host` and `port` are used if empty
+    pub listen: Vec<ListenAddr>,
+
     /// enables verbose mode
     pub verbose: bool,
 
@@ -40,6 +59,30 @@ pub struct Config {
 
     /// sets the config file (toml) to load the maps
     pub config: String,
+
+    /// reload the config when it changes, see `map::store::MapStore::watch`
+    pub watch: bool,
+
+    /// time to wait for data from a client, `None` waits forever
+    pub read_timeout: Option<Duration>,
+
+    /// time to wait while writing to a client, `None` waits forever
+    pub write_timeout: Option<Duration>,
+
+    /// number of clients served at the same time, 0 for no limit
+    pub max_connections: usize,
+
+    /// time the clients get to leave when the server stops
+    pub shutdown_timeout: Duration,
+
+    /// force colored output on or off, detected from the terminal if `None`
+    pub color: Option<bool>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Config {
@@ -48,22 +91,121 @@ impl Config {
         Self {
             port: 1996,
             host: "127.0.0.1".to_string(),
+            listen: Vec::new(),
             verbose: false,
             verbosity_level: 0,
             threads: 8,
             config: "./config.toml".to_string(),
+            watch: false,
+            read_timeout: None,
+            write_timeout: None,
+            max_connections: 0,
+            shutdown_timeout: Duration::from_secs(10),
+            color: None,
+        }
+    }
+
+    /// returns the addresses to listen on
+    ///
+    /// `host:port` if `listen` is empty, IPv6 hosts are put in brackets.
+    pub fn listen_addrs(&self) -> Vec<ListenAddr> {
+        if !self.listen.is_empty() {
+            return self.listen.clone();
+        }
+        let addr = if self.host.contains(':') && !self.host.starts_with('[') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        };
+        vec![ListenAddr::Tcp(addr)]
+    }
+
+    /// overrides every value set in the settings
+    ///
+    /// Called once per source, in the order config file, environment
+    /// variables, cli flags, so later sources win. A source setting `host` or
+    /// `port` without `listen` replaces the listen addresses of earlier sources.
+    pub fn apply(&mut self, settings: &settings::ServerSettings) {
+        let timeout = |secs: u64| match secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        if let Some(host) = &settings.host {
+            self.host = host.clone();
+            self.listen.clear();
+        }
+        if let Some(port) = settings.port {
+            self.port = port;
+            self.listen.clear();
+        }
+        if let Some(listen) = &settings.listen {
+            self.listen = listen.clone();
+        }
+        if let Some(threads) = settings.threads {
+            self.threads = threads;
+        }
+        if let Some(secs) = settings.read_timeout {
+            self.read_timeout = timeout(secs);
+        }
+        if let Some(secs) = settings.write_timeout {
+            self.write_timeout = timeout(secs);
+        }
+        if let Some(max) = settings.max_connections {
+            self.max_connections = max;
+        }
+        if let Some(secs) = settings.shutdown_timeout {
+            self.shutdown_timeout = Duration::from_secs(secs);
+        }
+        if let Some(watch) = settings.watch {
+            self.watch = watch;
+        }
+        if let Some(verbosity) = settings.log.verbosity {
+            self.verbosity_level = verbosity;
+            self.verbose = verbosity > 0;
+        }
+        if let Some(color) = settings.log.color {
+            self.color = Some(color);
         }
     }
+
+    /// starts the server in the background and returns a handle to control it
+    ///
+    /// The process is never exited, errors are returned. The output follows
+    /// `verbose` and `verbosity_level`: when both are off only errors of the
+    /// server itself, like a failing accept, are written to stderr. See
+    /// `server::handle::ServerHandle`.
+    pub fn start(&self) -> error::Result<ServerHandle> {
+        ServerHandle::start(self)
+    }
+
+    /// serves one client over stdin and stdout, for launchers like inetd
+    ///
+    /// Returns when the client quit or closed stdin. Stdout only carries the
+    /// protocol, logs are written to stderr.
+    pub fn serve_stdio(&self) -> error::Result<()> {
+        let maps = Arc::new(map::store::MapStore::new(&self.config, false)?);
+        maps.current().report().print();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        server::server_client(rx, 0, Arc::clone(&maps));
+        server::negotiate(server::Job {
+            stream: Box::new(server::transport::Stdio),
+            writer: server::transport::Writer::new(Box::new(server::transport::Stdio)),
+            verbose: false,
+            sender: tx,
+            maps,
+            thumbnails: Arc::new(server::http::Thumbnails::new()),
+        })
+    }
+
     /// run function serving as the main function of the librarie.AsMut
     ///
     /// The function takes the config from itself, and serves the server as descriped
-    /// in this config.
+    /// in this config. On SIGINT or SIGTERM it stops accepting, sends
+    /// `shutdown <seconds>` to every client speaking protocol 0.2.0 and returns
+    /// after the clients left or `shutdown_timeout` passed.
     pub fn run(&self) {
-        println!(
-            "Starting {} server on port: {}",
-            "PokeEscape".green(),
-            self.port.to_string().yellow()
-        );
+        println!("Starting {} server", "PokeEscape".green());
         println!(
             "{}: {}",
             "version".bold().white(),
@@ -74,70 +216,49 @@ impl Config {
         }
 
         // load maps
-        #[allow(unused_variables)]
-        let maps = match map::MapPlaces::new(&self.config, self.verbose) {
-            Ok(maps) => maps,
+        let maps = match map::store::MapStore::new(&self.config, self.verbose) {
+            Ok(maps) => Arc::new(maps),
             Err(err) => {
                 eprintln!("Error loading maps: {}", err.to_string().red());
                 std::process::exit(20);
             }
         };
+        maps.current().report().print();
 
-        // create ThreadPool
-        let mut thread_pool = threads::ThreadPool::new(self.threads).unwrap_or_else(|err| {
-            println!("Error creating threadPool: {}", err.to_string().red());
-            process::exit(-2);
+        // reload maps at runtime
+        #[cfg(unix)]
+        map::store::MapStore::reload_on_sighup(Arc::clone(&maps)).unwrap_or_else(|err| {
+            eprintln!("could not listen for SIGHUP: {}", err);
         });
+        if self.watch {
+            map::store::MapStore::watch(Arc::clone(&maps), WATCH_INTERVAL);
+        }
 
+        let server = ServerHandle::start_with_maps(self, maps).unwrap_or_else(|err| {
+            eprintln!("Error starting server: {}", err.to_string().red());
+            std::process::exit(20);
+        });
         if self.verbose {
-            thread_pool.verbose();
             println!(
                 "created {} with {} workers",
                 "ThreadPool".blue(),
-                thread_pool.get_threads().to_string().green()
+                self.threads.to_string().green()
             );
         }
+        for addr in server.local_addrs() {
+            println!("listening on {}", addr.to_string().green());
+        }
 
-        println!(
-            "listening on {}:{}",
-            self.host.green(),
-            self.port.to_string().green()
-        );
-        // open socket
-        let listener = TcpListener::bind(format!("{}:{}", self.host, self.port)).unwrap_or_else(|err| {
-            eprintln!("could not bind to port: {}", err);
-            std::process::exit(20);
-        }); //FIXME: !!!
-
-        // create channel
-        let (tx, rx) = mpsc::channel();
-
-        // create handle thread
-        server::server_client(rx, self.verbosity_level, maps); // FIXME: verbosity level
-
-        // handle incomming streams
-        for stream in listener.incoming() {
-            let stream = match stream {
-                Ok(stream) => stream,
-                Err(err) => {
-                    eprintln!("error creating stream: {}", err);
-                    continue;
-                }
-            };
-            let conf = server::Job {
-                stream,
-                verbose: self.verbose,
-                sender: mpsc::Sender::clone(&tx),
-            };
-
-            // execute Job in ThreadPool
-            thread_pool
-                .execute(move || {
-                    server::negotiate(conf).unwrap_or_else(|err| {
-                        eprintln!("error while executing client handler: {}", err)
-                    });
-                })
-                .unwrap(); // FIXME: unwrap
+        // stop on SIGINT and SIGTERM
+        #[cfg(unix)]
+        server::shutdown::stop_on_signals(server.events()).unwrap_or_else(|err| {
+            eprintln!("could not listen for SIGINT and SIGTERM: {}", err);
+        });
+
+        if let Err(err) = server.wait() {
+            eprintln!("Error stopping server: {}", err.to_string().red());
+            process::exit(1);
         }
+        println!("{} stopped", "PokeEscape".green());
     }
 }
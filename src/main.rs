@@ -1,4 +1,7 @@
 use clap::{App, AppSettings, Arg, SubCommand}; // clap for argument foo
+use poke_escape_server::map::report::{DiagnosticKind, LoadReport};
+use poke_escape_server::server::listener::ListenAddr; // addresses of --listen
+use poke_escape_server::settings::ServerSettings; // [Server] of the config file
 use poke_escape_server::Config; // config object (also hold cli arguments)
 
 fn main() {
@@ -35,6 +38,16 @@ fn main() {
                 .help("configures the inerface (ip) to listen on")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("listen")
+                .short("l")
+                .long("listen")
+                .help("address to listen on (host:port or unix:path), replaces host and port")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("threads")
                 .short("t")
@@ -42,6 +55,17 @@ fn main() {
                 .help("defines the number fo thread in ThreadPool to use")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("stdio")
+                .long("stdio")
+                .help("serve one client over stdin and stdout, for inetd"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .short("w")
+                .long("watch")
+                .help("reload the config when it changes"),
+        )
         .subcommand(
             SubCommand::with_name("license")
                 .about("show license")
@@ -77,6 +101,205 @@ fn main() {
                 .setting(clap::AppSettings::ColorAuto)
                 .setting(clap::AppSettings::ColoredHelp),
         )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("check the config and load every map")
+                .version("0.1.0")
+                .author("Finn Behrens <me@kloenk.de>")
+                .arg(
+                    Arg::with_name("warnings")
+                        .help("treat warnings as errors")
+                        .short("W")
+                        .long("warnings-as-errors"),
+                )
+                .setting(AppSettings::ColorAuto)
+                .setting(AppSettings::ColoredHelp),
+        )
+        .subcommand(
+            SubCommand::with_name("render")
+                .about("draw a map in the terminal")
+                .version("0.1.0")
+                .author("Finn Behrens <me@kloenk.de>")
+                .arg(
+                    Arg::with_name("map")
+                        .help("name of the map to draw")
+                        .index(1)
+                        .required(true)
+                        .value_name("MAP"),
+                )
+                .setting(AppSettings::ColorAuto)
+                .setting(AppSettings::ColoredHelp),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("convert a map into another format")
+                .version("0.1.0")
+                .author("Finn Behrens <me@kloenk.de>")
+                .arg(
+                    Arg::with_name("map")
+                        .help("name of the map in the config, or path with --file")
+                        .index(1)
+                        .required(true)
+                        .value_name("MAP"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .help("format to write")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("FORMAT")
+                        .possible_value("json")
+                        .possible_value("toml")
+                        .possible_value("text"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .help("read MAP as path of a map file instead of a name in the config")
+                        .short("f")
+                        .long("file"),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .help("format of the map file, guessed from the extension if not set")
+                        .long("from")
+                        .takes_value(true)
+                        .requires("file")
+                        .value_name("FORMAT")
+                        .possible_value("json")
+                        .possible_value("toml")
+                        .possible_value("text"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .help("sets output file")
+                        .value_name("FILE")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true),
+                )
+                .setting(AppSettings::ColorAuto)
+                .setting(AppSettings::ColoredHelp),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate-config")
+                .about("rewrite the config into the newest schema")
+                .version("0.1.0")
+                .author("Finn Behrens <me@kloenk.de>")
+                .arg(
+                    Arg::with_name("out")
+                        .help("sets output file, prints to stdout if not set")
+                        .value_name("FILE")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true),
+                )
+                .setting(AppSettings::ColorAuto)
+                .setting(AppSettings::ColoredHelp),
+        )
+        .subcommand(
+            SubCommand::with_name("client")
+                .about("connect to a running server and send commands interactively")
+                .version("0.1.0")
+                .author("Finn Behrens <me@kloenk.de>")
+                .arg(
+                    Arg::with_name("address")
+                        .help("address of the server (host:port or unix:path), the first listen address if not set")
+                        .index(1)
+                        .value_name("ADDRESS"),
+                )
+                .arg(
+                    Arg::with_name("history")
+                        .help("file keeping the command history, ~/.poke_escape_history if not set")
+                        .long("history")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                )
+                .setting(AppSettings::ColorAuto)
+                .setting(AppSettings::ColoredHelp),
+        )
+        .subcommand(
+            SubCommand::with_name("loadtest")
+                .about("simulate many clients and report how the server handles them")
+                .version("0.1.0")
+                .author("Finn Behrens <me@kloenk.de>")
+                .arg(
+                    Arg::with_name("address")
+                        .help("address of a running server (host:port or unix:path), starts a local server if not set")
+                        .index(1)
+                        .value_name("ADDRESS"),
+                )
+                .arg(
+                    Arg::with_name("clients")
+                        .help("number of simulated clients")
+                        .short("n")
+                        .long("clients")
+                        .takes_value(true)
+                        .validator(count)
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::with_name("duration")
+                        .help("seconds every client runs")
+                        .short("d")
+                        .long("duration")
+                        .takes_value(true)
+                        .validator(seconds)
+                        .default_value("10"),
+                )
+                .arg(
+                    Arg::with_name("rooms")
+                        .help("number of rooms the clients are spread over")
+                        .long("rooms")
+                        .takes_value(true)
+                        .validator(count)
+                        .default_value("4"),
+                )
+                .arg(
+                    Arg::with_name("map-rate")
+                        .help("maps loaded per second by every client")
+                        .long("map-rate")
+                        .takes_value(true)
+                        .validator(rate)
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("chat-rate")
+                        .help("chat messages per second by every client")
+                        .long("chat-rate")
+                        .takes_value(true)
+                        .validator(rate)
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("switch-rate")
+                        .help("room switches per second by every client")
+                        .long("switch-rate")
+                        .takes_value(true)
+                        .validator(rate)
+                        .default_value("0.5"),
+                )
+                .arg(
+                    Arg::with_name("map")
+                        .help("map to load, may be given more than once")
+                        .short("m")
+                        .long("map")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .default_value("random"),
+                )
+                .arg(
+                    Arg::with_name("timeout")
+                        .help("seconds to wait for an answer of the server")
+                        .long("timeout")
+                        .takes_value(true)
+                        .validator(seconds)
+                        .default_value("5"),
+                )
+                .setting(AppSettings::ColorAuto)
+                .setting(AppSettings::ColoredHelp),
+        )
         .setting(AppSettings::ColorAuto)
         .setting(AppSettings::ColoredHelp);
 
@@ -84,49 +307,130 @@ fn main() {
 
     // run subcommands
     if let Some(matches) = matches.subcommand_matches("completion") {
-        completion(&matches, &mut app);
+        completion(matches, &mut app);
         std::process::exit(0);
     }
 
     drop(app); // destroy app variable
 
-    if let Some(_) = matches.subcommand_matches("license") {
+    if matches.subcommand_matches("license").is_some() {
         show_license();
         std::process::exit(0);
     }
 
     let mut config = Config::new();
 
+    // config file: environment < cli flag
+    if let Ok(conf) = std::env::var("POKE_ESCAPE_CONFIG") {
+        config.config = conf;
+    }
     if let Some(conf) = matches.value_of("config") {
         config.config = conf.to_string();
     }
 
+    // defaults < config file < environment < cli flags
+    let mut settings = LoadReport::new(config.config.clone());
+    let sources = [
+        ("[Server]", ServerSettings::from_file(&config.config)),
+        ("environment", ServerSettings::from_env()),
+    ];
+    for (source, loaded) in sources {
+        match loaded {
+            Ok(loaded) => {
+                for name in &loaded.ignored {
+                    settings.warn(DiagnosticKind::UnknownSetting(name.clone()), None, None);
+                }
+                config.apply(&loaded);
+            }
+            Err(err) => {
+                let reason = format!("{}: {}", source, err);
+                settings.error(DiagnosticKind::InvalidConfig(reason), None, None);
+            }
+        }
+    }
+    config.apply(&cli_settings(&matches));
+
+    if let Some(color) = config.color {
+        colored::control::set_override(color);
+    }
+
+    // validate reports the settings with the maps
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        std::process::exit(validate(matches, &config, &settings));
+    }
+    settings.print();
+    if settings.has_errors() {
+        std::process::exit(1);
+    }
+
+    if let Some(matches) = matches.subcommand_matches("render") {
+        std::process::exit(render(matches, &config));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("convert") {
+        std::process::exit(convert(matches, &config));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("migrate-config") {
+        std::process::exit(migrate_config(matches, &config));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("client") {
+        std::process::exit(client(matches, &config));
+    }
+
+    if let Some(matches) = matches.subcommand_matches("loadtest") {
+        std::process::exit(loadtest(matches, &config));
+    }
+
+    if matches.is_present("stdio") {
+        if let Err(err) = config.serve_stdio() {
+            eprintln!("Error serving stdio: {}", err);
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
+    config.run(); // run server
+}
+
+/// returns the settings given as cli flags
+fn cli_settings(matches: &clap::ArgMatches) -> ServerSettings {
+    let mut settings = ServerSettings::default();
+
     // Vary the output based on how many times the user used the "verbose" flag
     // (i.e. 'myprog -v -v -v' or 'myprog -vvv' vs 'myprog -v'
     match matches.occurrences_of("verbose") {
-        // FIXME: more vebose options
-        0 => config.verbose = false,
-        1 | _ => config.verbose = true,
-    };
-    config.verbosity_level = matches.occurrences_of("verbose") as u8;
+        0 => (),
+        level => settings.log.verbosity = Some(level.min(u64::from(u8::MAX)) as u8),
+    }
 
     if let Some(port) = matches.value_of("port") {
         if let Ok(port) = port.parse::<u16>() {
-            config.port = port;
+            settings.port = Some(port);
         }
     }
 
-    if let Some(host) = matches.value_of("host") {
-        config.host = host.to_string();
+    settings.host = matches.value_of("host").map(|host| host.to_string());
+
+    if let Some(listen) = matches.values_of("listen") {
+        let listen: Result<Vec<ListenAddr>, _> = listen.map(|addr| addr.parse()).collect();
+        settings.listen = Some(listen.unwrap_or_else(|err| {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }));
     }
 
     if let Some(threads) = matches.value_of("threads") {
         if let Ok(threads) = threads.parse::<usize>() {
-            config.threads = threads;
+            settings.threads = Some(threads);
         }
     }
 
-    config.run(); // run server
+    if matches.is_present("watch") {
+        settings.watch = Some(true);
+    }
+    settings
 }
 
 // create completion
@@ -149,13 +453,13 @@ fn completion(args: &clap::ArgMatches, app: &mut App) {
     use clap::Shell;
     let shell_l = shell.to_lowercase();
     let shell: Shell;
-    if shell_l == "fish".to_string() {
+    if shell_l == "fish" {
         shell = Shell::Fish;
-    } else if shell_l == "zsh".to_string() {
+    } else if shell_l == "zsh" {
         shell = Shell::Zsh;
-    } else if shell_l == "powershell".to_string() {
+    } else if shell_l == "powershell" {
         shell = Shell::PowerShell;
-    } else if shell_l == "elvish".to_string() {
+    } else if shell_l == "elvish" {
         shell = Shell::Elvish;
     } else {
         shell = Shell::Bash;
@@ -166,18 +470,405 @@ fn completion(args: &clap::ArgMatches, app: &mut App) {
     use std::io::Write;
 
     let mut path = BufWriter::new(match args.value_of("out") {
-        Some(x) => Box::new(
-            File::create(&std::path::Path::new(x)).unwrap_or_else(|err| {
-                eprintln!("Error opening file: {}", err);
-                std::process::exit(1);
-            }),
-        ) as Box<Write>,
-        None => Box::new(std::io::stdout()) as Box<Write>,
+        Some(x) => Box::new(File::create(std::path::Path::new(x)).unwrap_or_else(|err| {
+            eprintln!("Error opening file: {}", err);
+            std::process::exit(1);
+        })) as Box<dyn Write>,
+        None => Box::new(std::io::stdout()) as Box<dyn Write>,
     });
 
     app.gen_completions_to("poke_escape_server", shell, &mut path);
 }
 
+/// loads the config and every map, prints a report and returns the exit code
+///
+/// `settings` holds what was found while reading the server settings.
+fn validate(args: &clap::ArgMatches, config: &Config, settings: &LoadReport) -> i32 {
+    use colored::*;
+    use poke_escape_server::map::MapPlaces;
+
+    println!("Validating {}", config.config.blue());
+    for entry in settings.entries() {
+        println!("  {}: {}", entry.colored_position(), entry.kind);
+    }
+    let maps = match MapPlaces::new(&config.config, false) {
+        Ok(maps) => maps,
+        Err(err) => {
+            println!("[{}]: {}", "failed".red(), err.to_string().red());
+            return 1;
+        }
+    };
+
+    let mut errors = settings.errors().count() + maps.report().errors().count();
+    let mut warnings = settings.warnings().count() + maps.report().warnings().count();
+    for entry in maps.report().entries() {
+        println!("  {}: {}", entry.colored_position(), entry.kind);
+    }
+
+    let results = maps.validate();
+    let mut failed = 0;
+    for (map, report) in &results {
+        match map {
+            Ok(map) => println!(
+                "{} [{}]: size {}, features: {}, {}",
+                report.source().green(),
+                "Ok".green(),
+                map.size().yellow(),
+                map.feature_list(),
+                match map.analysis().shortest_path() {
+                    Some(steps) if map.analysis().solvable() => {
+                        format!("solvable in {} steps", steps).green()
+                    }
+                    _ => "not solvable".yellow(),
+                }
+            ),
+            Err(_) => {
+                failed += 1;
+                println!("{} [{}]", report.source().red(), "failed".red());
+            }
+        }
+        for entry in report.entries() {
+            println!("  {}: {}", entry.colored_position(), entry.kind);
+        }
+        errors += report.errors().count();
+        warnings += report.warnings().count();
+    }
+
+    println!(
+        "{} maps, {} failed, {} errors, {} warnings",
+        results.len(),
+        failed.to_string().red(),
+        errors.to_string().red(),
+        warnings.to_string().yellow()
+    );
+
+    if errors > 0 || (warnings > 0 && args.is_present("warnings")) {
+        1
+    } else {
+        0
+    }
+}
+
+/// loads a map and draws it in the terminal, returns the exit code
+fn render(args: &clap::ArgMatches, config: &Config) -> i32 {
+    use colored::*;
+    use poke_escape_server::map::{render, MapPlaces};
+
+    let maps = match MapPlaces::new(&config.config, false) {
+        Ok(maps) => maps,
+        Err(err) => {
+            eprintln!("Error loading maps: {}", err.to_string().red());
+            return 1;
+        }
+    };
+
+    let name = args.value_of("map").unwrap_or_default();
+    let (map, report) = maps.get_with_report(name);
+    report.print();
+    match map {
+        Ok(map) => {
+            print!("{}", render::terminal(&map));
+            0
+        }
+        Err(err) => {
+            eprintln!(
+                "Error loading map {}: {}",
+                name.blue(),
+                err.to_string().red()
+            );
+            1
+        }
+    }
+}
+
+/// loads a map and writes it in another format, returns the exit code
+fn convert(args: &clap::ArgMatches, config: &Config) -> i32 {
+    use colored::*;
+    use poke_escape_server::map::{MapFormat, MapInfo, MapPlaces};
+    use std::str::FromStr;
+
+    let name = args.value_of("map").unwrap_or_default();
+    let (map, report) = if args.is_present("file") {
+        let format = match args.value_of("from") {
+            Some(format) => MapFormat::from_str(format).ok(),
+            None => MapFormat::from_extension(name),
+        };
+        let format = match format {
+            Some(format) => format,
+            None => {
+                eprintln!("Could not guess format of {}, use --from", name.blue());
+                return 1;
+            }
+        };
+        match MapInfo::from_file(name, format) {
+            Ok(info) => info.load_map_with_report(),
+            Err(err) => {
+                eprintln!(
+                    "Error loading map {}: {}",
+                    name.blue(),
+                    err.to_string().red()
+                );
+                return 1;
+            }
+        }
+    } else {
+        match MapPlaces::new(&config.config, false) {
+            Ok(maps) => maps.get_with_report(name),
+            Err(err) => {
+                eprintln!("Error loading maps: {}", err.to_string().red());
+                return 1;
+            }
+        }
+    };
+    report.print();
+    let map = match map {
+        Ok(map) => map,
+        Err(err) => {
+            eprintln!(
+                "Error loading map {}: {}",
+                name.blue(),
+                err.to_string().red()
+            );
+            return 1;
+        }
+    };
+
+    let to = args.value_of("to").unwrap_or("json");
+    let writer = match MapFormat::from_str(to).and_then(|f| f.writer()) {
+        Ok(writer) => writer,
+        Err(err) => {
+            eprintln!("Error: {}", err.to_string().red());
+            return 1;
+        }
+    };
+    let mut lossy = LoadReport::new(map.name());
+    let content = match writer.write(&map, &mut lossy) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Error writing map: {}", err.to_string().red());
+            return 1;
+        }
+    };
+    lossy.print();
+
+    match args.value_of("out") {
+        Some(out) => {
+            if let Err(err) = std::fs::write(out, content) {
+                eprintln!("Error writing file: {}", err);
+                return 1;
+            }
+            eprintln!(
+                "Converted {} to {} in {} ({} lossy)",
+                map.name().green(),
+                to.yellow(),
+                out.blue(),
+                lossy.entries().len()
+            );
+        }
+        None => print!("{}", content),
+    }
+    0
+}
+
+/// rewrites the config into the newest schema, returns the exit code
+fn migrate_config(args: &clap::ArgMatches, config: &Config) -> i32 {
+    use colored::*;
+    use poke_escape_server::map::schema::{self, ConfigSchema};
+
+    let content = match std::fs::read_to_string(&config.config) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Error reading {}: {}", config.config.blue(), err);
+            return 1;
+        }
+    };
+    let content: toml::Value = match toml::from_str(&content) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Error parsing {}: {}", config.config.blue(), err);
+            return 1;
+        }
+    };
+
+    let mut report = LoadReport::new(config.config.clone());
+    let migrated = match schema::migrate(&content, &mut report) {
+        Ok(migrated) => migrated,
+        Err(err) => {
+            eprintln!("Error migrating config: {}", err.to_string().red());
+            return 1;
+        }
+    };
+    report.print();
+    let migrated = match toml::to_string(&migrated) {
+        Ok(migrated) => migrated,
+        Err(err) => {
+            eprintln!("Error writing config: {}", err);
+            return 1;
+        }
+    };
+
+    match args.value_of("out") {
+        Some(out) => {
+            if let Err(err) = std::fs::write(out, migrated) {
+                eprintln!("Error writing file: {}", err);
+                return 1;
+            }
+            eprintln!(
+                "Migrated {} to schema {} in {}, comments are not kept",
+                config.config.green(),
+                ConfigSchema::LATEST.version().to_string().yellow(),
+                out.blue()
+            );
+        }
+        None => print!("{}", migrated),
+    }
+    0
+}
+
+/// runs the interactive debug client, returns the exit code
+fn client(args: &clap::ArgMatches, config: &Config) -> i32 {
+    use colored::*;
+    use poke_escape_server::client::repl;
+
+    let addr = match args.value_of("address") {
+        Some(addr) => match addr.parse::<ListenAddr>() {
+            Ok(addr) => addr,
+            Err(err) => {
+                eprintln!("Error: {}", err.to_string().red());
+                return 1;
+            }
+        },
+        None => config.listen_addrs().remove(0),
+    };
+    let history = match args.value_of("history") {
+        Some(history) => Some(std::path::PathBuf::from(history)),
+        None => std::env::var_os("HOME")
+            .map(|home| std::path::PathBuf::from(home).join(".poke_escape_history")),
+    };
+
+    match repl::run(&addr, history) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!(
+                "Error talking to {}: {}",
+                addr.to_string().blue(),
+                err.to_string().red()
+            );
+            1
+        }
+    }
+}
+
+/// validates a number of seconds greater than 0, like `1.5`
+///
+/// A timeout of 0 is an error for the sockets, a duration of 0 runs nothing.
+fn seconds(value: String) -> Result<(), String> {
+    match value.parse().map(std::time::Duration::try_from_secs_f64) {
+        Ok(Ok(secs)) if !secs.is_zero() => Ok(()),
+        _ => Err(format!("{} is no number of seconds greater than 0", value)),
+    }
+}
+
+/// validates a count of at least 1, like the number of clients
+fn count(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(count) if count > 0 => Ok(()),
+        _ => Err(format!("{} is no number of at least 1", value)),
+    }
+}
+
+/// validates a rate per second, 0 turns the action off
+fn rate(value: String) -> Result<(), String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate >= 0.0 => Ok(()),
+        _ => Err(format!("{} is no rate of at least 0", value)),
+    }
+}
+
+/// runs simulated clients against a server, returns the exit code
+fn loadtest(args: &clap::ArgMatches, config: &Config) -> i32 {
+    use colored::*;
+    use poke_escape_server::client::loadtest::{self, LoadSettings};
+    use std::time::Duration;
+
+    fn value<T: std::str::FromStr>(args: &clap::ArgMatches, name: &str) -> Option<T> {
+        let value = args.value_of(name).unwrap_or_default();
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                eprintln!("Error: invalid value {} for --{}", value.red(), name);
+                None
+            }
+        }
+    }
+    let settings = (|| {
+        Some(LoadSettings {
+            clients: value(args, "clients")?,
+            duration: Duration::from_secs_f64(value(args, "duration")?),
+            rooms: value(args, "rooms")?,
+            map_rate: value(args, "map-rate")?,
+            chat_rate: value(args, "chat-rate")?,
+            switch_rate: value(args, "switch-rate")?,
+            maps: args.values_of("map")?.map(String::from).collect(),
+            timeout: Duration::from_secs_f64(value(args, "timeout")?),
+        })
+    })();
+    let settings = match settings {
+        Some(settings) => settings,
+        None => return 1,
+    };
+
+    // without an address the clients run against a server in this process
+    let (addr, server) = match args.value_of("address") {
+        Some(addr) => match addr.parse::<ListenAddr>() {
+            Ok(addr) => (addr, None),
+            Err(err) => {
+                eprintln!("Error: {}", err.to_string().red());
+                return 1;
+            }
+        },
+        None => {
+            let mut local = config.clone();
+            local.listen = vec![ListenAddr::Tcp("127.0.0.1:0".to_string())];
+            if local.threads < settings.clients {
+                eprintln!(
+                    "{}: {} threads serve {} clients, the others wait for a free thread",
+                    "warning".yellow(),
+                    local.threads,
+                    settings.clients
+                );
+            }
+            match local.start() {
+                Ok(server) => (server.local_addrs()[0].clone(), Some(server)),
+                Err(err) => {
+                    eprintln!("Error starting local server: {}", err.to_string().red());
+                    return 1;
+                }
+            }
+        }
+    };
+
+    println!(
+        "running {} clients against {} for {}s",
+        settings.clients.to_string().yellow(),
+        addr.to_string().green(),
+        settings.duration.as_secs_f64()
+    );
+    let report = loadtest::run(&addr, &settings);
+    print!("{}", report);
+
+    if let Some(server) = server {
+        if let Err(err) = server.shutdown() {
+            eprintln!("Error stopping local server: {}", err.to_string().red());
+        }
+    }
+    if report.error_count() > 0 {
+        1
+    } else {
+        0
+    }
+}
+
 /// prints the license to stdout
 pub fn show_license() {
     println!(
@@ -0,0 +1,200 @@
+use super::report::{DiagnosticKind, LoadReport};
+use super::tile::Tile;
+use super::WIDTH;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// position of a cell as `(row, column)`, zero based
+pub type Position = (usize, usize);
+
+/// Analysis holds the result of the path-finding over a map
+///
+/// Players walk to the four neighbour cells, solid blocks (1) are walls.
+/// Every teleport (11) is linked to every other teleport on the map, stepping
+/// through a teleport counts as one step. The exit is every cell on the border
+/// of the map which is neither a solid block nor a start point, so a player
+/// starting on the border still has to walk out.
+///
+/// # Example
+/// ```
+/// use poke_escape_server::map::analysis::Analysis;
+/// let mut rows = vec![[1; 28]; 3];
+/// rows[1][1] = 5;
+/// rows[1][2] = 0;
+/// rows[1][27] = 0;
+/// let analysis = Analysis::new(&rows);
+/// assert!(!analysis.solvable());
+///
+/// for cell in rows[1][2..27].iter_mut() {
+///     *cell = 0;
+/// }
+/// let analysis = Analysis::new(&rows);
+/// assert!(analysis.solvable());
+/// assert_eq!(analysis.shortest_path(), Some(26));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Analysis {
+    /// true if every start point can reach the exit
+    p_solvable: bool,
+
+    /// start points of the map
+    p_starts: Vec<Position>,
+
+    /// start points without a path to the exit
+    p_stuck_starts: Vec<Position>,
+
+    /// number of exit cells on the border of the map
+    p_exits: usize,
+
+    /// berries no start point can reach
+    p_unreachable_berries: Vec<Position>,
+
+    /// number of steps from the nearest start point to the exit
+    p_shortest_path: Option<usize>,
+}
+
+impl Analysis {
+    /// runs the path-finding over the rows of a map
+    pub fn new(rows: &[[u8; WIDTH]]) -> Self {
+        let starts = find(rows, |code| code == Tile::Start.code());
+        let exits: Vec<Position> = cells(rows)
+            .filter(|&(y, x)| y == 0 || x == 0 || y + 1 == rows.len() || x + 1 == WIDTH)
+            .filter(|&(y, x)| rows[y][x] != Tile::Solid.code() && rows[y][x] != Tile::Start.code())
+            .collect();
+
+        // the graph is undirected, so the distance from the exit is the
+        // distance to the exit
+        let to_exit = distances(rows, &exits);
+        let from_start = distances(rows, &starts);
+
+        let stuck_starts: Vec<Position> = starts
+            .iter()
+            .filter(|&&(y, x)| to_exit[y][x].is_none())
+            .cloned()
+            .collect();
+        let shortest_path = starts.iter().filter_map(|&(y, x)| to_exit[y][x]).min();
+        let unreachable_berries = find(rows, |code| {
+            Tile::from_code(code).is_some_and(|tile| tile.is_berry())
+        })
+        .into_iter()
+        .filter(|&(y, x)| from_start[y][x].is_none())
+        .collect();
+
+        Analysis {
+            p_solvable: !starts.is_empty() && stuck_starts.is_empty(),
+            p_starts: starts,
+            p_stuck_starts: stuck_starts,
+            p_exits: exits.len(),
+            p_unreachable_berries: unreachable_berries,
+            p_shortest_path: shortest_path,
+        }
+    }
+
+    /// returns true if the map has a start point and every start point can reach the exit
+    pub fn solvable(&self) -> bool {
+        self.p_solvable
+    }
+
+    /// returns the start points of the map
+    pub fn starts(&self) -> &[Position] {
+        &self.p_starts
+    }
+
+    /// returns the start points without a path to the exit
+    pub fn stuck_starts(&self) -> &[Position] {
+        &self.p_stuck_starts
+    }
+
+    /// returns the number of exit cells
+    pub fn exits(&self) -> usize {
+        self.p_exits
+    }
+
+    /// returns the berries no start point can reach
+    pub fn unreachable_berries(&self) -> &[Position] {
+        &self.p_unreachable_berries
+    }
+
+    /// returns the number of steps from the nearest start point to the exit
+    ///
+    /// `None` if no start point can reach the exit.
+    pub fn shortest_path(&self) -> Option<usize> {
+        self.p_shortest_path
+    }
+
+    /// records every problem found as warning in the report
+    pub fn report(&self, report: &mut LoadReport) {
+        if self.p_starts.is_empty() {
+            report.warn(DiagnosticKind::NoStart, None, None);
+        }
+        if self.p_exits == 0 {
+            report.warn(DiagnosticKind::NoExit, None, None);
+        } else {
+            for &(y, x) in &self.p_stuck_starts {
+                report.warn(DiagnosticKind::StuckStart, Some(y), Some(x));
+            }
+        }
+        for &(y, x) in &self.p_unreachable_berries {
+            report.warn(DiagnosticKind::UnreachableBerry, Some(y), Some(x));
+        }
+    }
+}
+
+/// returns every position of the map
+fn cells(rows: &[[u8; WIDTH]]) -> impl Iterator<Item = Position> {
+    let height = rows.len();
+    (0..height).flat_map(|y| (0..WIDTH).map(move |x| (y, x)))
+}
+
+/// returns every position holding a code matching the filter
+fn find<F: Fn(u8) -> bool>(rows: &[[u8; WIDTH]], filter: F) -> Vec<Position> {
+    cells(rows).filter(|&(y, x)| filter(rows[y][x])).collect()
+}
+
+/// returns the number of steps from the nearest source for every cell
+///
+/// Cells which cannot be reached, and solid blocks, are `None`.
+pub fn distances(rows: &[[u8; WIDTH]], sources: &[Position]) -> Vec<[Option<usize>; WIDTH]> {
+    let mut dist = vec![[None; WIDTH]; rows.len()];
+    let mut queue = VecDeque::new();
+    for &(y, x) in sources {
+        if rows[y][x] != Tile::Solid.code() && dist[y][x].is_none() {
+            dist[y][x] = Some(0);
+            queue.push_back((y, x));
+        }
+    }
+
+    let teleports = find(rows, |code| code == Tile::Teleport.code());
+    let mut teleported = false;
+
+    while let Some((y, x)) = queue.pop_front() {
+        let steps = dist[y][x].unwrap_or(0) + 1;
+        let mut next = Vec::with_capacity(4);
+        if y > 0 {
+            next.push((y - 1, x));
+        }
+        if y + 1 < rows.len() {
+            next.push((y + 1, x));
+        }
+        if x > 0 {
+            next.push((y, x - 1));
+        }
+        if x + 1 < WIDTH {
+            next.push((y, x + 1));
+        }
+        // the first teleport found is the nearest, so all others are linked from it
+        if rows[y][x] == Tile::Teleport.code() && !teleported {
+            teleported = true;
+            next.extend(teleports.iter().cloned());
+        }
+
+        for (ny, nx) in next {
+            if dist[ny][nx].is_none() && rows[ny][nx] != Tile::Solid.code() {
+                dist[ny][nx] = Some(steps);
+                queue.push_back((ny, nx));
+            }
+        }
+    }
+
+    dist
+}
@@ -0,0 +1,45 @@
+use super::{features, write_rows, LoadReport, Map, MapLoader, MapWriter, RawMap, Result};
+use toml::Value;
+
+/// JsonLoader reads maps written in json
+///
+/// # Format
+/// ```json
+/// {
+///     "name": "Hello World",
+///     "features": ["none"],
+///     "map": [
+///         [1, 1, 1, 1],
+///         [1, 5, 0, 1]
+///     ]
+/// }
+/// ```
+pub struct JsonLoader;
+
+impl MapLoader for JsonLoader {
+    fn parse(&self, content: &str) -> Result<RawMap> {
+        let content: Value = serde_json::from_str(content)?;
+        RawMap::from_value(&content)
+    }
+}
+
+impl MapWriter for JsonLoader {
+    /// writes the map as canonical json, one row per line
+    fn write(&self, map: &Map, _report: &mut LoadReport) -> Result<String> {
+        let mut names = Vec::new();
+        for feature in features(map) {
+            names.push(serde_json::to_string(&feature)?);
+        }
+        let mut meta = String::new();
+        for (key, value) in map.meta().entries() {
+            meta += &format!("    \"{}\": {},\n", key, serde_json::to_string(&value)?);
+        }
+        Ok(format!(
+            "{{\n    \"name\": {},\n    \"features\": [{}],\n{}    \"map\": [\n{}\n    ]\n}}\n",
+            serde_json::to_string(&map.p_name)?,
+            names.join(", "),
+            meta,
+            write_rows(map, "        ", ",\n")
+        ))
+    }
+}
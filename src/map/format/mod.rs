@@ -0,0 +1,301 @@
+use ::toml::Value;
+use semver::Version;
+use std::str::FromStr;
+
+use super::super::error::{Error, ErrorKind};
+use super::meta::MapMeta;
+use super::report::LoadReport;
+use super::Map;
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// loader for maps written in json
+pub mod json;
+
+/// loader for maps written in toml
+pub mod toml;
+
+/// loader for maps drawn as ascii art
+pub mod text;
+
+/// loader for maps exported from the Tiled editor
+pub mod tiled;
+
+/// MapLoader parses the content of a map file
+///
+/// There is one implementation per `MapFormat`. A loader only translates the
+/// file into a `RawMap`, the checks of the `LoadMode` run on the `RawMap`
+/// afterwards, so every format is checked the same way.
+pub trait MapLoader {
+    /// parse the content of a map file
+    fn parse(&self, content: &str) -> Result<RawMap>;
+}
+
+/// MapWriter writes a `Map` in a map file format
+///
+/// The written map is always `WIDTH` wide. Everything which cannot be written
+/// in the format is recorded as `DiagnosticKind::Lossy` in the report.
+pub trait MapWriter {
+    /// write the map into a string
+    fn write(&self, map: &Map, report: &mut LoadReport) -> Result<String>;
+}
+
+/// a single cell of a `RawMap`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Cell {
+    /// cell holding a number, not checked if it is a known tile code
+    Code(i64),
+
+    /// cell which is not a number (or an unknown glyph in the text format)
+    Invalid,
+
+    /// tile of the source format without a mapping to a tile code, holds the tile id
+    Unmapped(u32),
+}
+
+/// RawMap holds a map as read from the file, before any check ran
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RawMap {
+    /// name written in the file, `None` if the format has no name
+    pub name: Option<String>,
+
+    /// features written in the file
+    pub features: Option<Vec<String>>,
+
+    /// rows of the map, not padded or cropped to `WIDTH`
+    pub rows: Vec<Vec<Cell>>,
+
+    /// metadata written in the file
+    pub meta: MapMeta,
+
+    /// version written in the file, used for maps found in `Maps.directory`
+    pub version: Option<Version>,
+
+    /// authors written in the file, used for maps found in `Maps.directory`
+    pub author: Option<Vec<String>>,
+}
+
+impl RawMap {
+    /// reads a `RawMap` from a value with the keys `name`, `features` and `map`
+    ///
+    /// This is the layout shared by the json and the toml format. The optional
+    /// keys `version`, `author` and the keys of `MapMeta` are read from the
+    /// same level.
+    pub fn from_value(content: &Value) -> Result<Self> {
+        // get name
+        let name: String = match content.get("name") {
+            Some(name) => match name.as_str() {
+                Some(name) => name.to_string(),
+                None => return Err(Error::new_field_not_exists("name".to_string())),
+            },
+            None => return Err(Error::new_field_not_exists("name".to_string())),
+        };
+
+        // get feature list
+        let features: Option<Vec<String>> = match content.get("features") {
+            Some(Value::Array(f)) => {
+                let mut fe = Vec::new();
+                for v in f {
+                    match v.as_str() {
+                        Some(v) => fe.push(v.to_string()),
+                        None => {
+                            return Err(Error::new_field_not_exists(
+                                "features is not string".to_string(),
+                            ))
+                        }
+                    }
+                }
+                Some(fe)
+            }
+            Some(Value::String(f)) => Some(vec![f.clone()]),
+            _ => None,
+        };
+
+        // load map
+        let j_map = match content.get("map") {
+            Some(j) => j,
+            None => return Err(Error::new_field_not_exists("map".to_string())),
+        };
+        let j_map = match j_map.as_array() {
+            Some(j) => j,
+            None => return Err(Error::new_field_not_exists("map".to_string())),
+        };
+        let mut rows = Vec::new();
+        for v in j_map {
+            let v = match v.as_array() {
+                Some(j) => j,
+                None => return Err(Error::new_field_not_exists("map".to_string())),
+            };
+            rows.push(
+                v.iter()
+                    .map(|b| match b.as_integer() {
+                        Some(b) => Cell::Code(b),
+                        None => Cell::Invalid,
+                    })
+                    .collect(),
+            );
+        }
+
+        let version = match content.get("version") {
+            Some(version) => match version.as_str() {
+                Some(version) => Some(Version::from_str(version)?),
+                None => return Err(Error::new_field_not_exists("version".to_string())),
+            },
+            None => None,
+        };
+
+        let author = match content.get("author") {
+            Some(Value::String(author)) => Some(vec![author.clone()]),
+            Some(Value::Array(authors)) => {
+                let mut ret = Vec::new();
+                for author in authors {
+                    match author.as_str() {
+                        Some(author) => ret.push(author.to_string()),
+                        None => return Err(Error::new_field_not_exists("author".to_string())),
+                    }
+                }
+                Some(ret)
+            }
+            Some(_) => return Err(Error::new_field_not_exists("author".to_string())),
+            None => None,
+        };
+
+        Ok(RawMap {
+            meta: MapMeta::from_value(content, &name)?,
+            version,
+            author,
+            name: Some(name),
+            features,
+            rows,
+        })
+    }
+}
+
+/// map format is a enum of possible loader elements for a map file
+///
+/// The format of a map is set with the `format` key in the table of the map
+/// in the config. Without `format` the map is read as json.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MapFormat {
+    /// JSON as the javascript object notation
+    JSON,
+
+    /// TOML with the same keys as the json format
+    TOML,
+
+    /// ascii art, one character per tile, see `text`
+    Text,
+
+    /// json export of the Tiled editor, see `tiled`
+    Tiled(tiled::TiledSettings),
+
+    /// no file, the map is built by the generator, see `generator`
+    Random(super::generator::GeneratorSettings),
+}
+
+impl MapFormat {
+    /// returns the loader for the format
+    ///
+    /// # Errors
+    /// Returns `FormatNotSupported` for generated maps, as they have no file.
+    pub fn loader(&self) -> Result<Box<dyn MapLoader>> {
+        match self {
+            MapFormat::JSON => Ok(Box::new(json::JsonLoader)),
+            MapFormat::TOML => Ok(Box::new(toml::TomlLoader)),
+            MapFormat::Text => Ok(Box::new(text::TextLoader)),
+            MapFormat::Tiled(settings) => Ok(Box::new(tiled::TiledLoader {
+                settings: settings.clone(),
+            })),
+            MapFormat::Random(_) => Err(Error::new(ErrorKind::FormatNotSupported)),
+        }
+    }
+}
+
+impl MapFormat {
+    /// returns the writer for the format
+    ///
+    /// # Errors
+    /// Returns `FormatNotSupported` for formats which can only be imported.
+    pub fn writer(&self) -> Result<Box<dyn MapWriter>> {
+        match self {
+            MapFormat::JSON => Ok(Box::new(json::JsonLoader)),
+            MapFormat::TOML => Ok(Box::new(toml::TomlLoader)),
+            MapFormat::Text => Ok(Box::new(text::TextLoader)),
+            MapFormat::Tiled(_) | MapFormat::Random(_) => {
+                Err(Error::new(ErrorKind::FormatNotSupported))
+            }
+        }
+    }
+
+    /// guesses the format of a map file from the extension of the path
+    ///
+    /// `.map` files are json. The Tiled format is never guessed, as it needs
+    /// the settings from the config.
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::map::format::MapFormat;
+    /// assert_eq!(MapFormat::from_extension("./maps/wald.map"), Some(MapFormat::JSON));
+    /// assert_eq!(MapFormat::from_extension("./maps/wald.txt"), Some(MapFormat::Text));
+    /// assert_eq!(MapFormat::from_extension("./maps/wald"), None);
+    /// ```
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        match extension.to_lowercase().as_str() {
+            "map" | "json" => Some(MapFormat::JSON),
+            "toml" => Some(MapFormat::TOML),
+            "txt" | "text" => Some(MapFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+/// writes the rows of a map as `[1, 2, 3]` lines, shared by json and toml
+fn write_rows(map: &Map, indent: &str, separator: &str) -> String {
+    let rows: Vec<String> = map
+        .rows()
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+            format!("{}[{}]", indent, cells.join(", "))
+        })
+        .collect();
+    rows.join(separator)
+}
+
+/// returns the features of the map for writing, `none` if the map has no features
+fn features(map: &Map) -> Vec<String> {
+    match &map.p_features {
+        Some(features) => features.clone(),
+        None => vec!["none".to_string()],
+    }
+}
+
+impl FromStr for MapFormat {
+    type Err = Error;
+
+    /// parses the name of the format case insensitive
+    ///
+    /// The Tiled and the random format are returned with default settings, they
+    /// are read from the table of the map with `TiledSettings::from_conf` and
+    /// `GeneratorSettings::from_conf`.
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::map::format::MapFormat;
+    /// use std::str::FromStr;
+    /// assert_eq!(MapFormat::from_str("Json").unwrap(), MapFormat::JSON);
+    /// assert!(MapFormat::from_str("xml").is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(MapFormat::JSON),
+            "toml" => Ok(MapFormat::TOML),
+            "text" | "txt" => Ok(MapFormat::Text),
+            "tiled" => Ok(MapFormat::Tiled(tiled::TiledSettings::default())),
+            "random" => Ok(MapFormat::Random(Default::default())),
+            _ => Err(Error::new(ErrorKind::FormatNotSupported)),
+        }
+    }
+}
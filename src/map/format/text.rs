@@ -0,0 +1,217 @@
+use super::super::meta::{self, MapMeta};
+use super::super::report::{DiagnosticKind, LoadReport};
+use super::super::tile::Tile;
+use super::{features, Cell, Error, ErrorKind, Map, MapLoader, MapWriter, RawMap, Result};
+use semver::Version;
+use std::str::FromStr;
+use toml::value::{Table, Value};
+
+/// line between the header and the map
+pub const SEPARATOR: &str = "---";
+
+/// TextLoader reads maps drawn as ascii art
+///
+/// The file starts with a header of `key: value` lines, followed by a line
+/// holding only `---` and the map. Every character of the map is one tile,
+/// using the glyphs of `Tile::glyph`. Lines starting with `;` in the header
+/// are comments.
+///
+/// # Format
+/// ```text
+/// name: Hello World
+/// features: none
+/// ---
+/// ####
+/// #S.#
+/// #~T#
+/// ```
+///
+/// # Glyphs
+/// | Glyph | Code | Glyph | Code | Glyph | Code |
+/// |-------|------|-------|------|-------|------|
+/// | `.`   | 0    | `S`   | 5    | `E`   | 9    |
+/// | `#`   | 1    | `e`   | 6    | `F`   | 10   |
+/// | `~`   | 2    | `h`   | 7    | `T`   | 11   |
+/// | `^`   | 3    | `x`   | 8    | `-`   | 12   |
+/// | `=`   | 4    |       |      |       |      |
+///
+/// `features` and `author` are comma separated lists, `version` is used if
+/// the map is found in `Maps.directory`. The keys of `MapMeta` can be set in
+/// the header as well, `tags` is a comma separated list:
+/// ```text
+/// name: Hello World
+/// difficulty: easy
+/// tags: tutorial, small
+/// play_time: 5
+/// ---
+/// ```
+pub struct TextLoader;
+
+impl MapLoader for TextLoader {
+    fn parse(&self, content: &str) -> Result<RawMap> {
+        let mut lines = content.lines();
+        let mut map = RawMap::default();
+        let mut header = Table::new();
+
+        // read header
+        loop {
+            let line = match lines.next() {
+                Some(line) => line.trim(),
+                None => return Err(Error::new_field_not_exists(SEPARATOR.to_string())),
+            };
+            if line == SEPARATOR {
+                break;
+            }
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let (key, value) = match line.find(':') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => {
+                    return Err(Error::new(ErrorKind::NotParsable(format!(
+                        "header line without ':': {}",
+                        line
+                    ))))
+                }
+            };
+            match key {
+                "name" => map.name = Some(value.to_string()),
+                "features" => {
+                    map.features = Some(
+                        value
+                            .split(',')
+                            .map(|f| f.trim().to_string())
+                            .filter(|f| !f.is_empty())
+                            .collect(),
+                    )
+                }
+                "version" => map.version = Some(Version::from_str(value)?),
+                "author" => {
+                    map.author = Some(
+                        value
+                            .split(',')
+                            .map(|a| a.trim().to_string())
+                            .filter(|a| !a.is_empty())
+                            .collect(),
+                    )
+                }
+                "tags" => {
+                    let tags = value
+                        .split(',')
+                        .map(|t| t.trim())
+                        .filter(|t| !t.is_empty())
+                        .map(|t| Value::String(t.to_string()))
+                        .collect();
+                    header.insert(key.to_string(), Value::Array(tags));
+                }
+                key if meta::KEYS.contains(&key) => {
+                    let value = match value.parse::<i64>() {
+                        Ok(number) => Value::Integer(number),
+                        Err(_) => Value::String(value.to_string()),
+                    };
+                    header.insert(key.to_string(), value);
+                }
+                _ => (), // unknown keys are ignored, so new keys don't break old servers
+            }
+        }
+
+        let name = match &map.name {
+            Some(name) => name,
+            None => return Err(Error::new_field_not_exists("name".to_string())),
+        };
+        map.meta = MapMeta::from_value(&Value::Table(header), name)?;
+
+        // read map
+        for line in lines {
+            map.rows.push(
+                line.trim_end()
+                    .chars()
+                    .map(|c| match Tile::from_glyph(c) {
+                        Some(tile) => Cell::Code(i64::from(tile.code())),
+                        None => Cell::Invalid,
+                    })
+                    .collect(),
+            );
+        }
+        // empty lines at the end of the file are not part of the map
+        while map.rows.last().map(|r| r.is_empty()) == Some(true) {
+            map.rows.pop();
+        }
+
+        Ok(map)
+    }
+}
+
+impl MapWriter for TextLoader {
+    /// writes the map as ascii art
+    ///
+    /// Tile codes without a glyph are written as solid block, features
+    /// containing a `,` are split into two.
+    fn write(&self, map: &Map, report: &mut LoadReport) -> Result<String> {
+        let mut name = map.p_name.clone();
+        if name.contains('\n') {
+            report.warn(
+                DiagnosticKind::Lossy("line break in name replaced by space".to_string()),
+                None,
+                None,
+            );
+            name = name.replace('\n', " ");
+        }
+        let features = features(map);
+        if features.iter().any(|f| f.contains(',') || f.contains('\n')) {
+            report.warn(
+                DiagnosticKind::Lossy("feature containing ',' or line break".to_string()),
+                None,
+                None,
+            );
+        }
+
+        let mut out = format!(
+            "name: {}\nfeatures: {}\n",
+            name,
+            features.join(", ").replace('\n', " ")
+        );
+        for (key, value) in map.meta().entries() {
+            let value = match value {
+                Value::String(value) => value,
+                Value::Array(tags) => {
+                    let tags: Vec<&str> = tags.iter().filter_map(Value::as_str).collect();
+                    tags.join(", ")
+                }
+                value => value.to_string(),
+            };
+            if value.contains('\n')
+                || (key == "tags" && map.meta().tags.iter().any(|t| t.contains(',')))
+            {
+                report.warn(
+                    DiagnosticKind::Lossy(format!("line break or ',' in {}", key)),
+                    None,
+                    None,
+                );
+            }
+            out += &format!("{}: {}\n", key, value.replace('\n', " "));
+        }
+        out += SEPARATOR;
+        out.push('\n');
+        for (y, row) in map.rows().iter().enumerate() {
+            for (x, code) in row.iter().enumerate() {
+                match Tile::from_code(*code) {
+                    Some(tile) => out.push(tile.glyph()),
+                    None => {
+                        report.warn(
+                            DiagnosticKind::Lossy(format!(
+                                "tile code {} has no glyph, written as solid block",
+                                code
+                            )),
+                            Some(y),
+                            Some(x),
+                        );
+                        out.push(Tile::Solid.glyph());
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
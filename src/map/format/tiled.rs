@@ -0,0 +1,182 @@
+use super::{Cell, Error, ErrorKind, MapLoader, RawMap, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// bits Tiled uses in a GID to flip or rotate a tile
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+/// TiledSettings holds the per map settings for maps exported from the Tiled editor
+///
+/// The settings are read from the table of the map in the config:
+/// ```toml
+/// ["Level 1"]
+/// version = "0.1.0"
+/// path = "./maps/level1.json"
+/// format = "tiled"
+/// layer = "ground"
+///
+/// ["Level 1".tiles]
+/// 0 = 0
+/// 1 = 1
+/// 2 = 2
+/// 7 = 5
+/// ```
+/// `layer` is the name of the tile layer to read, without `layer` the first
+/// tile layer is used. `tiles` maps Tiled GIDs to Poké Escape tile codes.
+/// GID 0 (no tile in Tiled) is read as code 0 if it is not mapped.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TiledSettings {
+    /// name of the tile layer to read
+    pub layer: Option<String>,
+
+    /// maps Tiled GIDs to tile codes
+    pub tiles: HashMap<u32, u8>,
+}
+
+impl TiledSettings {
+    /// reads the settings from the toml table of the map
+    ///
+    /// # Parameters
+    /// `toml` - The toml Value of the given Map
+    /// `name` - Name of the map, used in errors
+    pub fn from_conf(toml: &toml::Value, name: &str) -> Result<Self> {
+        let layer = match toml.get("layer") {
+            Some(layer) => match layer.as_str() {
+                Some(layer) => Some(layer.to_string()),
+                None => return Err(Error::new_field_not_exists(format!("{}.layer", name))),
+            },
+            None => None,
+        };
+
+        let table = match toml.get("tiles") {
+            Some(tiles) => match tiles.as_table() {
+                Some(tiles) => tiles,
+                None => return Err(Error::new_field_not_exists(format!("{}.tiles", name))),
+            },
+            None => return Err(Error::new_field_not_exists(format!("{}.tiles", name))),
+        };
+
+        let mut tiles = HashMap::new();
+        for (gid, code) in table {
+            let gid = gid.parse::<u32>().map_err(|_| {
+                Error::new(ErrorKind::NotParsable(format!(
+                    "{}.tiles: {} is not a GID",
+                    name, gid
+                )))
+            })?;
+            let code = match code.as_integer() {
+                Some(code) if (0..=i64::from(u8::MAX)).contains(&code) => code as u8,
+                _ => {
+                    return Err(Error::new(ErrorKind::NotParsable(format!(
+                        "{}.tiles.{}: not a tile code",
+                        name, gid
+                    ))))
+                }
+            };
+            tiles.insert(gid, code);
+        }
+
+        Ok(TiledSettings { layer, tiles })
+    }
+}
+
+/// TiledLoader reads maps exported as json from the Tiled editor
+///
+/// Only orthogonal, finite maps with the layer data written as array (the CSV
+/// layer format) are supported. The string properties `name` and `features`
+/// (comma separated) of the map are read if they exist.
+pub struct TiledLoader {
+    /// settings of the map
+    pub settings: TiledSettings,
+}
+
+impl TiledLoader {
+    /// reads the string property with the given name of the Tiled map
+    fn property(content: &Value, name: &str) -> Option<String> {
+        content
+            .get("properties")?
+            .as_array()?
+            .iter()
+            .find(|p| p.get("name").and_then(Value::as_str) == Some(name))?
+            .get("value")?
+            .as_str()
+            .map(|v| v.to_string())
+    }
+
+    /// translates a Tiled GID to a cell
+    fn cell(&self, gid: u32) -> Cell {
+        let gid = gid & !FLIP_FLAGS;
+        match self.settings.tiles.get(&gid) {
+            Some(code) => Cell::Code(i64::from(*code)),
+            None if gid == 0 => Cell::Code(0),
+            None => Cell::Unmapped(gid),
+        }
+    }
+}
+
+impl MapLoader for TiledLoader {
+    fn parse(&self, content: &str) -> Result<RawMap> {
+        let content: Value = serde_json::from_str(content)?;
+
+        let layers = match content.get("layers").and_then(Value::as_array) {
+            Some(layers) => layers,
+            None => return Err(Error::new_field_not_exists("layers".to_string())),
+        };
+        let layer = layers
+            .iter()
+            .filter(|l| l.get("type").and_then(Value::as_str) == Some("tilelayer"))
+            .find(|l| match &self.settings.layer {
+                Some(name) => l.get("name").and_then(Value::as_str) == Some(name.as_str()),
+                None => true,
+            });
+        let layer = match layer {
+            Some(layer) => layer,
+            None => {
+                return Err(Error::new_field_not_exists(format!(
+                    "layers.{}",
+                    self.settings.layer.as_deref().unwrap_or("tilelayer")
+                )))
+            }
+        };
+
+        let width = match layer.get("width").and_then(Value::as_u64) {
+            Some(width) if width > 0 => width as usize,
+            _ => return Err(Error::new_field_not_exists("layer.width".to_string())),
+        };
+        let data = match layer.get("data") {
+            Some(Value::Array(data)) => data,
+            Some(_) => {
+                return Err(Error::new(ErrorKind::NotParsable(
+                    "encoded layers are not supported, export the layer as CSV".to_string(),
+                )))
+            }
+            None => return Err(Error::new_field_not_exists("layer.data".to_string())),
+        };
+
+        let rows = data
+            .chunks(width)
+            .map(|row| {
+                row.iter()
+                    .map(|gid| match gid.as_u64() {
+                        Some(gid) if gid <= u64::from(u32::MAX) => self.cell(gid as u32),
+                        _ => Cell::Invalid,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let features = Self::property(&content, "features").map(|f| {
+            f.split(',')
+                .map(|f| f.trim().to_string())
+                .filter(|f| !f.is_empty())
+                .collect()
+        });
+
+        Ok(RawMap {
+            name: Self::property(&content, "name"),
+            features,
+            rows,
+            ..Default::default()
+        })
+    }
+}
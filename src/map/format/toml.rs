@@ -0,0 +1,46 @@
+use super::{features, write_rows, LoadReport, Map, MapLoader, MapWriter, RawMap, Result};
+use toml::Value;
+
+/// TomlLoader reads maps written in toml
+///
+/// # Format
+/// ```toml
+/// name = "Hello World"
+/// features = ["none"]
+/// map = [
+///     [1, 1, 1, 1],
+///     [1, 5, 0, 1],
+/// ]
+/// ```
+pub struct TomlLoader;
+
+impl MapLoader for TomlLoader {
+    fn parse(&self, content: &str) -> Result<RawMap> {
+        let content: Value = ::toml::from_str(content)?;
+        RawMap::from_value(&content)
+    }
+}
+
+impl MapWriter for TomlLoader {
+    /// writes the map as toml, one row per line
+    fn write(&self, map: &Map, _report: &mut LoadReport) -> Result<String> {
+        let features: Vec<Value> = features(map).into_iter().map(Value::String).collect();
+        let mut meta = String::new();
+        for (key, value) in map.meta().entries() {
+            meta += &format!("{} = {}\n", key, value);
+        }
+        // an empty map has no row to put the separator after
+        let rows = if map.rows().is_empty() {
+            "[]".to_string()
+        } else {
+            format!("[\n{},\n]", write_rows(map, "    ", ",\n"))
+        };
+        Ok(format!(
+            "name = {}\nfeatures = {}\n{}map = {}\n",
+            Value::String(map.p_name.clone()),
+            Value::Array(features),
+            meta,
+            rows
+        ))
+    }
+}
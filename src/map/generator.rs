@@ -0,0 +1,252 @@
+use super::analysis::{self, Analysis};
+use super::tile::Tile;
+use super::{Map, WIDTH};
+
+use super::super::error::{Error, ErrorKind};
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// GeneratorSettings describes the maps built by `generate`
+///
+/// The settings are read from the table of a map with `format = "random"`:
+/// ```toml
+/// [random]
+/// version = "0.1.0"
+/// format = "random"
+/// seed = 1996
+/// height = 20
+/// wall_density = 0.2
+/// enemies = 3
+/// berries = 5
+/// teleports = 2
+/// ```
+/// Every key is optional and defaults to the values shown above.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratorSettings {
+    /// seed used if the map is requested without a seed
+    pub seed: u64,
+
+    /// number of rows, the width is always `WIDTH`
+    pub height: usize,
+
+    /// chance of an inner cell to be a solid block, between 0.0 and 0.9
+    pub wall_density: f64,
+
+    /// number of enemies to place
+    pub enemies: usize,
+
+    /// number of berries to place
+    pub berries: usize,
+
+    /// number of teleports to place
+    pub teleports: usize,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        GeneratorSettings {
+            seed: 1996,
+            height: 20,
+            wall_density: 0.2,
+            enemies: 3,
+            berries: 5,
+            teleports: 2,
+        }
+    }
+}
+
+impl GeneratorSettings {
+    /// reads the settings from the toml table of the map
+    ///
+    /// # Parameters
+    /// `toml` - The toml Value of the given Map
+    /// `name` - Name of the map, used in errors
+    pub fn from_conf(toml: &toml::Value, name: &str) -> Result<Self> {
+        let mut settings = Self::default();
+
+        let int = |key: &str, default: u64| -> Result<u64> {
+            match toml.get(key) {
+                Some(value) => match value.as_integer() {
+                    Some(value) if value >= 0 => Ok(value as u64),
+                    _ => Err(Error::new_field_not_exists(format!("{}.{}", name, key))),
+                },
+                None => Ok(default),
+            }
+        };
+        settings.seed = int("seed", settings.seed)?;
+        settings.height = int("height", settings.height as u64)? as usize;
+        settings.enemies = int("enemies", settings.enemies as u64)? as usize;
+        settings.berries = int("berries", settings.berries as u64)? as usize;
+        settings.teleports = int("teleports", settings.teleports as u64)? as usize;
+
+        if let Some(density) = toml.get("wall_density") {
+            // `wall_density = 0` is written without a fraction
+            settings.wall_density = match (density.as_float(), density.as_integer()) {
+                (Some(density), _) => density,
+                (None, Some(density)) => density as f64,
+                (None, None) => {
+                    return Err(Error::new(ErrorKind::NotParsable(format!(
+                        "{}.wall_density: must be a number between 0.0 and 0.9",
+                        name
+                    ))))
+                }
+            };
+        }
+
+        if settings.height < 3 {
+            return Err(Error::new(ErrorKind::NotParsable(format!(
+                "{}.height: needs at least 3 rows",
+                name
+            ))));
+        }
+        if !(0.0..=0.9).contains(&settings.wall_density) {
+            return Err(Error::new(ErrorKind::NotParsable(format!(
+                "{}.wall_density: must be a number between 0.0 and 0.9",
+                name
+            ))));
+        }
+
+        Ok(settings)
+    }
+}
+
+/// turns the seed requested by a client into a number
+///
+/// Numbers are used as they are, every other text is hashed (FNV-1a), so a
+/// room name can be used as seed as well.
+///
+/// # Example
+/// ```
+/// use poke_escape_server::map::generator::seed_from_str;
+/// assert_eq!(seed_from_str("42"), 42);
+/// assert_eq!(seed_from_str("room"), seed_from_str("room"));
+/// ```
+pub fn seed_from_str(seed: &str) -> u64 {
+    match seed.parse::<u64>() {
+        Ok(seed) => seed,
+        Err(_) => seed.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        }),
+    }
+}
+
+/// small deterministic random number generator (SplitMix64)
+///
+/// The generator is part of the crate, so a seed creates the same map on
+/// every platform and with every version of the dependencies.
+struct Rng(u64);
+
+impl Rng {
+    /// returns the next random number
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// returns a random number in `0..max`
+    fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+
+    /// returns true with the given chance
+    fn chance(&mut self, chance: f64) -> bool {
+        ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < chance
+    }
+}
+
+/// builds a map from the seed and the settings
+///
+/// The map is surrounded by solid blocks, with one opening in the right wall
+/// as exit. It always has exactly one start point (5), and a path from the
+/// start point to the exit is kept free of solid blocks. Berries, enemies
+/// and teleports are only placed on cells reachable from the start point.
+/// The same seed and settings always create the same map.
+///
+/// # Example
+/// ```
+/// use poke_escape_server::map::generator::{generate, GeneratorSettings};
+/// let settings = GeneratorSettings::default();
+/// let a = generate("random 42", &settings, 42);
+/// let b = generate("random 42", &settings, 42);
+/// assert_eq!(a.rows(), b.rows());
+/// ```
+pub fn generate(name: &str, settings: &GeneratorSettings, seed: u64) -> Map {
+    let height = settings.height.max(3);
+    let mut rng = Rng(seed);
+    let mut map = vec![[Tile::None.code(); WIDTH]; height];
+
+    // walls
+    for (y, row) in map.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let border = x == 0 || y == 0 || x == WIDTH - 1 || y == height - 1;
+            if border || rng.chance(settings.wall_density) {
+                *cell = Tile::Solid.code();
+            }
+        }
+    }
+
+    // start in the left third, exit in the right wall
+    let inner = height - 2;
+    let (start_x, start_y) = (1 + rng.below((WIDTH - 2) / 3), 1 + rng.below(inner));
+    let exit_y = 1 + rng.below(inner);
+    map[exit_y][WIDTH - 1] = Tile::None.code();
+
+    // carve a path from the start to the exit
+    let (mut x, mut y) = (start_x, start_y);
+    map[y][x] = Tile::None.code();
+    while (x, y) != (WIDTH - 2, exit_y) {
+        if y != exit_y && (x == WIDTH - 2 || rng.chance(0.5)) {
+            if y < exit_y {
+                y += 1;
+            } else {
+                y -= 1;
+            }
+        } else {
+            x += 1;
+        }
+        map[y][x] = Tile::None.code();
+    }
+    map[start_y][start_x] = Tile::Start.code();
+
+    // free cells reachable from the start
+    let dist = analysis::distances(&map, &[(start_y, start_x)]);
+    let mut free: Vec<(usize, usize)> = (0..height)
+        .flat_map(|y| (1..WIDTH - 1).map(move |x| (x, y)))
+        .filter(|&(x, y)| dist[y][x].is_some() && map[y][x] == Tile::None.code())
+        .collect();
+
+    let berries = [Tile::BerryEnergy, Tile::BerryHp, Tile::BerryXp];
+    let mut items = Vec::new();
+    for _ in 0..settings.berries {
+        items.push(berries[rng.below(berries.len())]);
+    }
+    for _ in 0..settings.enemies {
+        items.push(if rng.chance(0.25) {
+            Tile::FastEnemy
+        } else {
+            Tile::Enemy
+        });
+    }
+    for _ in 0..settings.teleports {
+        items.push(Tile::Teleport);
+    }
+    for tile in items {
+        if free.is_empty() {
+            break; // map is full
+        }
+        let (x, y) = free.swap_remove(rng.below(free.len()));
+        map[y][x] = tile.code();
+    }
+
+    Map {
+        p_name: name.to_string(),
+        p_features: Some(vec!["random".to_string()]),
+        p_meta: Default::default(),
+        p_analysis: Analysis::new(&map),
+        p_map: map,
+    }
+}
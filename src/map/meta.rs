@@ -0,0 +1,252 @@
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use toml::Value;
+
+use super::super::error::{Error, ErrorKind};
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// keys of the metadata, in the order they are written
+pub const KEYS: [&str; 7] = [
+    "description",
+    "difficulty",
+    "min_players",
+    "max_players",
+    "tags",
+    "play_time",
+    "thumbnail",
+];
+
+/// how hard a map is to win
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    /// for new players
+    Easy,
+
+    /// for players knowing the game
+    Normal,
+
+    /// for experienced players
+    Hard,
+
+    /// for players looking for a challenge
+    Expert,
+}
+
+impl FromStr for Difficulty {
+    type Err = Error;
+
+    /// parses the name of the difficulty case insensitive
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            "expert" => Ok(Difficulty::Expert),
+            _ => Err(Error::new(ErrorKind::NotParsable(format!(
+                "unknown difficulty {}",
+                s
+            )))),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    /// standart formater for print! macro
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Normal => write!(f, "normal"),
+            Difficulty::Hard => write!(f, "hard"),
+            Difficulty::Expert => write!(f, "expert"),
+        }
+    }
+}
+
+/// MapMeta holds the informations a client needs to show a map browser
+///
+/// The metadata can be written into the table of the map in the config and
+/// into the map file with the same keys:
+/// ```toml
+/// ["Hello World"]
+/// version = "0.1.0"
+/// path = "./maps/Hello_World.map"
+/// description = "A small map to learn the controls"
+/// difficulty = "easy"
+/// min_players = 1
+/// max_players = 4
+/// tags = ["tutorial", "small"]
+/// play_time = 5
+/// thumbnail = "./maps/Hello_World.png"
+/// ```
+/// Every key is optional. `difficulty` is one of `easy`, `normal`, `hard` or
+/// `expert`, `play_time` is the estimated play time in minutes. Keys set in
+/// the config win over keys set in the map file.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MapMeta {
+    /// text describing the map
+    pub description: Option<String>,
+
+    /// how hard the map is to win
+    pub difficulty: Option<Difficulty>,
+
+    /// recommended minimum number of players
+    pub min_players: Option<u32>,
+
+    /// recommended maximum number of players
+    pub max_players: Option<u32>,
+
+    /// free tags to filter maps with
+    pub tags: Vec<String>,
+
+    /// estimated play time in minutes
+    pub play_time: Option<u32>,
+
+    /// path or url of a preview image
+    pub thumbnail: Option<String>,
+}
+
+impl MapMeta {
+    /// reads the metadata from a table, unknown keys are ignored
+    ///
+    /// # Parameters
+    /// `toml` - table of the map in the config, or the content of the map file
+    /// `name` - name used in errors
+    pub fn from_value(toml: &Value, name: &str) -> Result<Self> {
+        let string = |key: &str| -> Result<Option<String>> {
+            match toml.get(key) {
+                Some(value) => match value.as_str() {
+                    Some(value) => Ok(Some(value.to_string())),
+                    None => Err(Error::new_field_not_exists(format!("{}.{}", name, key))),
+                },
+                None => Ok(None),
+            }
+        };
+        let number = |key: &str| -> Result<Option<u32>> {
+            match toml.get(key) {
+                Some(value) => match value.as_integer() {
+                    Some(value) if (1..=i64::from(u32::MAX)).contains(&value) => {
+                        Ok(Some(value as u32))
+                    }
+                    _ => Err(Error::new(ErrorKind::NotParsable(format!(
+                        "{}.{}: must be a positive number",
+                        name, key
+                    )))),
+                },
+                None => Ok(None),
+            }
+        };
+
+        let difficulty = match string("difficulty")? {
+            Some(difficulty) => Some(Difficulty::from_str(&difficulty).map_err(|_| {
+                Error::new(ErrorKind::NotParsable(format!(
+                    "{}.difficulty: {} is not easy, normal, hard or expert",
+                    name, difficulty
+                )))
+            })?),
+            None => None,
+        };
+
+        let tags = match toml.get("tags") {
+            Some(Value::Array(tags)) => {
+                let mut ret = Vec::new();
+                for tag in tags {
+                    match tag.as_str() {
+                        Some(tag) => ret.push(tag.to_string()),
+                        None => return Err(Error::new_field_not_exists(format!("{}.tags", name))),
+                    }
+                }
+                ret
+            }
+            Some(Value::String(tag)) => vec![tag.clone()],
+            Some(_) => return Err(Error::new_field_not_exists(format!("{}.tags", name))),
+            None => Vec::new(),
+        };
+
+        let meta = MapMeta {
+            description: string("description")?,
+            difficulty,
+            min_players: number("min_players")?,
+            max_players: number("max_players")?,
+            tags,
+            play_time: number("play_time")?,
+            thumbnail: string("thumbnail")?,
+        };
+
+        if let (Some(min), Some(max)) = (meta.min_players, meta.max_players) {
+            if min > max {
+                return Err(Error::new(ErrorKind::NotParsable(format!(
+                    "{}.min_players: bigger than max_players",
+                    name
+                ))));
+            }
+        }
+
+        Ok(meta)
+    }
+
+    /// returns the metadata, with every key not set taken from `fallback`
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::map::meta::MapMeta;
+    /// let config = MapMeta { play_time: Some(10), ..Default::default() };
+    /// let file = MapMeta { play_time: Some(5), max_players: Some(4), ..Default::default() };
+    /// let meta = config.or(file);
+    /// assert_eq!(meta.play_time, Some(10));
+    /// assert_eq!(meta.max_players, Some(4));
+    /// ```
+    pub fn or(self, fallback: MapMeta) -> MapMeta {
+        MapMeta {
+            description: self.description.or(fallback.description),
+            difficulty: self.difficulty.or(fallback.difficulty),
+            min_players: self.min_players.or(fallback.min_players),
+            max_players: self.max_players.or(fallback.max_players),
+            tags: if self.tags.is_empty() {
+                fallback.tags
+            } else {
+                self.tags
+            },
+            play_time: self.play_time.or(fallback.play_time),
+            thumbnail: self.thumbnail.or(fallback.thumbnail),
+        }
+    }
+
+    /// returns true if no key is set
+    pub fn is_empty(&self) -> bool {
+        *self == MapMeta::default()
+    }
+
+    /// returns the keys which are set with their value, in the order of `KEYS`
+    ///
+    /// Used by the map writers, so converting a map keeps its metadata.
+    pub fn entries(&self) -> Vec<(&'static str, Value)> {
+        let mut entries = Vec::new();
+        if let Some(description) = &self.description {
+            entries.push((KEYS[0], Value::String(description.clone())));
+        }
+        if let Some(difficulty) = self.difficulty {
+            entries.push((KEYS[1], Value::String(difficulty.to_string())));
+        }
+        if let Some(min) = self.min_players {
+            entries.push((KEYS[2], Value::Integer(i64::from(min))));
+        }
+        if let Some(max) = self.max_players {
+            entries.push((KEYS[3], Value::Integer(i64::from(max))));
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().cloned().map(Value::String).collect();
+            entries.push((KEYS[4], Value::Array(tags)));
+        }
+        if let Some(play_time) = self.play_time {
+            entries.push((KEYS[5], Value::Integer(i64::from(play_time))));
+        }
+        if let Some(thumbnail) = &self.thumbnail {
+            entries.push((KEYS[6], Value::String(thumbnail.clone())));
+        }
+        entries
+    }
+}
@@ -1,6 +1,6 @@
 use colored::*;
 use semver::Version;
-use serde_derive::Serialize;
+use serde_derive::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::str::FromStr;
@@ -11,12 +11,56 @@ use super::error::Error;
 #[doc(inline)]
 pub use super::error::Result;
 
+/// structured warnings and errors of the map loader
+pub mod report;
+
+/// tiles a map is made of
+pub mod tile;
+
+/// draws maps for humans
+pub mod render;
+
+/// loaders for the supported map file formats
+pub mod format;
+
+/// builds random maps from a seed
+pub mod generator;
+
+/// shared maps of the running server, reloaded at runtime
+pub mod store;
+
+/// versions of config.toml and migrations between them
+pub mod schema;
+
+/// path-finding to check if a map can be won
+pub mod analysis;
+
+/// informations about a map for a map browser
+pub mod meta;
+
+#[doc(inline)]
+pub use format::MapFormat;
+
+use format::Cell;
+
+// tests as sub module
+#[cfg(test)] // only add when running tests
+mod test;
+
+use analysis::Analysis;
+use meta::MapMeta;
+use report::{DiagnosticKind, LoadReport};
+use schema::ConfigSchema;
+
 /// defines the widht of the map
 ///
 /// creates warning and 0 an the right end if map in file is smaller,
 /// or crops the right side if map is bigger
 pub const WIDTH: usize = 28;
 
+/// highest tile code described in the table on `Map`
+pub const MAX_TILE: u8 = 12;
+
 /// struct holding all informations of the toml file
 // this is not a deserialisable struct, so every map can live at top level
 pub struct MapPlaces {
@@ -25,11 +69,22 @@ pub struct MapPlaces {
 
     /// stores available (set for loading) maps
     p_maps: HashMap<String, MapInfo>,
+
+    /// diagnostics raised while reading the toml file
+    p_report: LoadReport,
+
+    /// directory searched for maps, set with `Maps.directory`
+    p_directory: Option<String>,
 }
 
 impl MapPlaces {
     /// Loads toml file and deserialize it into `MapPlaces`
     ///
+    /// If `Maps.directory` is set, the map files in the directory are added
+    /// to the maps listed in `Maps.maps`, see `MapInfo::from_directory`.
+    /// Configured maps win over maps found in the directory. With a directory
+    /// `Maps.maps` can be left out.
+    ///
     /// # Arguments
     /// - `file` -> give the path of the toml file to load
     /// - `verbose` -> if set true shows status of loading
@@ -38,10 +93,12 @@ impl MapPlaces {
             println!("Loading {} from {}", "Maps".green(), file.blue());
         }
 
+        let mut report = LoadReport::new(file.to_string());
+
         let file = fs::read_to_string(file)?; //FIXME: testing
         let content: Value = toml::from_str(file.as_str())?;
 
-        if content.get("Maps") == None {
+        if content.get("Maps").is_none() {
             return Err(Error::new_field_not_exists("Maps".to_string()));
         }
 
@@ -51,27 +108,94 @@ impl MapPlaces {
         };
         let version = Version::from_str(version)?;
 
-        let mut maps = HashMap::new();
+        // default load mode of all maps
+        let mode = match content["Maps"].get("mode") {
+            Some(mode) => match mode.as_str() {
+                Some(mode) => LoadMode::from_str(mode)?,
+                None => return Err(Error::new_field_not_exists("Maps.mode".to_string())),
+            },
+            None => LoadMode::default(),
+        };
 
-        if version < Version::new(99, 99, 99) {
-            // check in reserve order for version
-            maps = MapInfo::from_conf(&content, verbose)?;
+        let schema = ConfigSchema::for_version(&version)?;
+        let mut maps = schema.load(&content, mode, verbose, &mut report)?;
+
+        // maps found in the directory
+        let directory = match content["Maps"].get("directory") {
+            Some(directory) => match directory.as_str() {
+                Some(directory) => Some(directory.to_string()),
+                None => return Err(Error::new_field_not_exists("Maps.directory".to_string())),
+            },
+            None => None,
+        };
+        if let Some(directory) = &directory {
+            match MapInfo::from_directory(directory, mode, verbose, &mut report) {
+                Ok(found) => Self::merge(&mut maps, found, &mut report),
+                Err(err) => report.error(
+                    DiagnosticKind::InvalidConfig(format!("Maps.directory {}: {}", directory, err)),
+                    None,
+                    None,
+                ),
+            }
         }
+
         Ok(MapPlaces {
             p_version: version,
             p_maps: maps,
+            p_report: report,
+            p_directory: directory,
         })
     }
 
-    /// list all maps described in this `MapPlaces`
-    pub fn available_maps(&self) -> Vec<String> {
-        let mut vec = Vec::new();
+    /// adds the maps found in `Maps.directory` to the configured maps
+    ///
+    /// # Conflicts
+    /// - a file used as `path` by a configured map is skipped without a warning
+    /// - a map named like a configured map is skipped, the config wins
+    /// - of two files with the same name, the first path in sorted order wins
+    ///
+    /// Skipped maps are recorded as `DiagnosticKind::Conflict` warnings.
+    fn merge(maps: &mut HashMap<String, MapInfo>, found: Vec<MapInfo>, report: &mut LoadReport) {
+        let same_file = |a: &str, b: &str| match (fs::canonicalize(a), fs::canonicalize(b)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => a == b,
+        };
 
-        for (k, _) in &self.p_maps {
-            vec.push(k.clone());
+        for map in found {
+            if maps.values().any(|m| same_file(&m.p_file, &map.p_file)) {
+                continue;
+            }
+            if maps.contains_key(map.name()) {
+                report.warn(
+                    DiagnosticKind::Conflict {
+                        name: map.p_name.clone(),
+                        path: map.p_file.clone(),
+                    },
+                    None,
+                    None,
+                );
+                continue;
+            }
+            maps.insert(map.name().clone(), map);
         }
+    }
 
-        vec // return map names
+    /// returns the directory searched for maps, if `Maps.directory` is set
+    pub fn directory(&self) -> Option<&str> {
+        self.p_directory.as_deref()
+    }
+
+    /// list all maps described in this `MapPlaces`
+    pub fn available_maps(&self) -> Vec<String> {
+        self.p_maps.keys().cloned().collect() // return map names
+    }
+
+    /// returns the diagnostics raised while reading the toml file
+    ///
+    /// Maps that could not be read from the config are not available,
+    /// the report tells why.
+    pub fn report(&self) -> &LoadReport {
+        &self.p_report
     }
 
     /// returns the pointer to a specific map
@@ -88,14 +212,64 @@ impl MapPlaces {
     ///
     /// This function take a name as argument and returns the loaded Map
     /// associated with the given name
+    ///
+    /// Generated maps can be requested with a seed after the name, like
+    /// `random 42`. The same seed always returns the same map.
     pub fn get(&self, name: &str) -> Result<Map> {
-        match self.p_maps.get(name) {
-            // FIXME: version foo for random feature
-            Some(data) => data.load_map(),
-            None => Err(Error::new_field_not_exists(name.to_string())),
+        self.get_with_report(name).0
+    }
+
+    /// looks up the map info and the seed for a requested name
+    fn lookup<'a>(&self, name: &'a str) -> Option<(&MapInfo, Option<&'a str>)> {
+        if let Some(info) = self.p_maps.get(name) {
+            return Some((info, None));
+        }
+        let split = name.trim_end().rfind(' ')?;
+        match self.p_maps.get(name[..split].trim_end()) {
+            Some(info) if matches!(info.p_format, MapFormat::Random(_)) => {
+                Some((info, Some(name[split + 1..].trim())))
+            }
+            _ => None,
         }
     }
 
+    /// return the Map with the given Name and the report of the loader
+    ///
+    /// Like `get`, but also returns every warning and error found while loading
+    /// the map. The report is returned even if loading failed.
+    pub fn get_with_report(&self, name: &str) -> (Result<Map>, LoadReport) {
+        match self.lookup(name) {
+            Some((data, seed)) => data.load_map_seeded(seed),
+            None => {
+                let mut report = LoadReport::new(name.to_string());
+                report.error(
+                    DiagnosticKind::MissingInConfig(name.to_string()),
+                    None,
+                    None,
+                );
+                (Err(Error::new_field_not_exists(name.to_string())), report)
+            }
+        }
+    }
+
+    /// force-loads every available map
+    ///
+    /// Returns the result and the report of every map, sorted by map name.
+    pub fn validate(&self) -> Vec<(Result<Map>, LoadReport)> {
+        let mut names = self.available_maps();
+        names.sort();
+        names
+            .iter()
+            .map(|name| {
+                let (map, mut report) = self.get_with_report(name);
+                if let Ok(map) = &map {
+                    map.analysis().report(&mut report);
+                }
+                (map, report)
+            })
+            .collect()
+    }
+
     /// returns the author of the map
     pub fn get_author(&self, name: &str) -> Option<String> {
         match self.p_maps.get(name) {
@@ -104,6 +278,13 @@ impl MapPlaces {
         }
     }
 
+    /// returns the metadata of the map set in the config
+    ///
+    /// Metadata only written in the map file is returned by `Map::meta`.
+    pub fn get_meta(&self, name: &str) -> Option<&MapMeta> {
+        self.p_maps.get(name).map(MapInfo::meta)
+    }
+
     /// returns the version of the map
     pub fn version(&self) -> &Version {
         &self.p_version
@@ -128,14 +309,34 @@ impl MapPlaces {
 /// | 10     | Enemy           | 150% speed; 120% damage |
 /// | 11     | Teleport        |                         |
 /// | 12     | Moving platform |                         |
-#[derive(Serialize)]
+///
+/// The codes are available as `tile::Tile`.
+/// The map is sent together with its `MapMeta` and its `Analysis`.
+#[derive(Serialize, Deserialize)]
 pub struct Map {
     p_name: String,
     p_features: Option<Vec<String>>,
     p_map: Vec<[u8; WIDTH]>,
+    #[serde(default)]
+    p_meta: MapMeta,
+    #[serde(default)]
+    p_analysis: Analysis,
 }
 
 impl Map {
+    /// reads a map in the json sent to the clients, see `Display`
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::map::Map;
+    /// let map = Map::from_json(r#"{"p_name": "a", "p_features": null, "p_map": [[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]]}"#).unwrap();
+    /// assert_eq!(map.name(), "a");
+    /// assert_eq!(map.size(), "28x1");
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
     /// returns the name of the map
     pub fn name(&self) -> String {
         self.p_name.clone() // returns a clone
@@ -146,6 +347,33 @@ impl Map {
         format!("{}x{}", WIDTH, self.p_map.len())
     }
 
+    /// returns the rows of the map, every row holds `WIDTH` tile codes
+    pub fn rows(&self) -> &[[u8; WIDTH]] {
+        &self.p_map
+    }
+
+    /// returns a hash of the name and the tiles of the map
+    ///
+    /// Maps with the same digest are drawn the same, so it is used to cache
+    /// rendered images.
+    pub fn digest(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.p_name.hash(&mut hasher);
+        self.p_map.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// returns the metadata of the map
+    pub fn meta(&self) -> &MapMeta {
+        &self.p_meta
+    }
+
+    /// returns the result of the path-finding over the map
+    pub fn analysis(&self) -> &Analysis {
+        &self.p_analysis
+    }
+
     /// check if the feature exists
     pub fn feature(&self, feature: &String) -> bool {
         match &self.p_features {
@@ -180,7 +408,7 @@ impl std::fmt::Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let json = match serde_json::to_string(self) {
             Ok(json) => json,
-            Err(err) => format!("{{\"status\": 100, \"err\": \"{}\"}}", err.to_string()),
+            Err(err) => format!("{{\"status\": 100, \"err\": \"{}\"}}", err),
         };
         write!(f, "{}", json)
     }
@@ -189,7 +417,7 @@ impl std::fmt::Display for Map {
 /// this holds a single map with all of the coresponding informations
 ///
 /// This function willnot preload the map, but loads it when used (random??)
-struct MapInfo {
+pub struct MapInfo {
     /// name of the given map
     p_name: String,
 
@@ -200,7 +428,6 @@ struct MapInfo {
     p_version: Version,
 
     /// format of the given map file
-    #[allow(dead_code)]
     p_format: MapFormat,
 
     /// author of the map
@@ -208,6 +435,12 @@ struct MapInfo {
 
     /// set to true if the mapload should operate in verbose mode
     p_verbose: bool,
+
+    /// checks to run while loading the map
+    p_mode: LoadMode,
+
+    /// metadata set in the config
+    p_meta: MapMeta,
 }
 
 impl MapInfo {
@@ -228,30 +461,123 @@ impl MapInfo {
             p_format: format,
             p_author: author,
             p_verbose: verbose,
+            p_mode: LoadMode::default(),
+            p_meta: MapMeta::default(),
         }
     }
 
-    /// Read toml value and returns a HashMap with the maps specified in the toml file
-    pub fn from_conf(toml: &toml::Value, verbose: bool) -> Result<HashMap<String, Self>> {
-        if toml["Maps"].get("maps") == None {
-            // check if maps definition exists
-            return Err(Error::new_field_not_exists("Maps.maps".to_string()));
+    /// creates the info for a map file which is not listed in the config
+    ///
+    /// The name is read from the file, formats without a name use the file
+    /// stem. The map is loaded with the default `LoadMode`.
+    pub fn from_file(file: &str, format: MapFormat) -> Result<Self> {
+        let content = fs::read_to_string(file)?;
+        let raw = format.loader()?.parse(&content)?;
+        let name = match raw.name {
+            Some(name) => name,
+            None => std::path::Path::new(file)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| file.to_string()),
+        };
+        Ok(Self::new(
+            name,
+            file.to_string(),
+            raw.version.unwrap_or_else(|| Version::new(0, 1, 0)),
+            format,
+            raw.author,
+            false,
+        ))
+    }
+
+    /// finds the map files in a directory
+    ///
+    /// Every file with an extension known to `MapFormat::from_extension` is
+    /// read with `from_file`, other files are ignored. Sub directories are not
+    /// searched. Files which cannot be read are skipped and recorded in
+    /// `report`. The maps are returned sorted by path.
+    pub fn from_directory(
+        directory: &str,
+        mode: LoadMode,
+        verbose: bool,
+        report: &mut LoadReport,
+    ) -> Result<Vec<Self>> {
+        let mut paths: Vec<String> = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.is_file() {
+                paths.push(path.to_string_lossy().to_string());
+            }
         }
+        paths.sort();
 
-        let maps_names = match toml["Maps"]["maps"].as_array() {
+        let mut maps = Vec::new();
+        for path in paths {
+            let format = match MapFormat::from_extension(&path) {
+                Some(format) => format,
+                None => continue,
+            };
+            if verbose {
+                print!("Loading infos of map file {}... ", path.blue());
+            }
+            match Self::from_file(&path, format) {
+                Ok(mut map) => {
+                    if verbose {
+                        println!("[{}]: Name: {}", "Ok".green(), map.name().green());
+                    }
+                    map.p_verbose = verbose;
+                    map.set_mode(mode);
+                    maps.push(map);
+                }
+                Err(err) => {
+                    if verbose {
+                        println!("[{}]: {}", "failed".red(), err.to_string().red());
+                    }
+                    report.error(
+                        DiagnosticKind::InvalidConfig(format!("{}: {}", path, err)),
+                        None,
+                        None,
+                    );
+                }
+            }
+        }
+        Ok(maps)
+    }
+
+    /// Read toml value and returns a HashMap with the maps specified in the toml file
+    ///
+    /// Maps which cannot be read are skipped and recorded in `report`.
+    /// `mode` is used for every map which does not set its own `mode`.
+    pub fn from_conf(
+        toml: &toml::Value,
+        mode: LoadMode,
+        verbose: bool,
+        report: &mut LoadReport,
+    ) -> Result<HashMap<String, Self>> {
+        let no_maps = Vec::new();
+        let maps_names = match toml["Maps"].get("maps") {
             // get maps definition
-            Some(maps) => maps,
+            Some(maps) => match maps.as_array() {
+                Some(maps) => maps,
+                None => return Err(Error::new_field_not_exists("Maps.maps".to_string())),
+            },
+            // all maps can come from the directory
+            None if toml["Maps"].get("directory").is_some() => &no_maps,
             None => return Err(Error::new_field_not_exists("Maps.maps".to_string())),
         };
 
         let mut maps = HashMap::new();
 
         // parse maps
-        for map in maps_names {
+        for (i, map) in maps_names.iter().enumerate() {
             let map = match map.as_str() {
                 Some(map) => map,
                 None => {
-                    eprintln!("Map name could not be converted to string");
+                    report.error(
+                        DiagnosticKind::InvalidConfig(format!("Maps.maps[{}] is not a string", i)),
+                        None,
+                        None,
+                    );
                     continue; // parse next map
                 }
             };
@@ -261,54 +587,132 @@ impl MapInfo {
             }
 
             // check for metadata of map
-            if toml.get(map) == None {
+            if toml.get(map).is_none() {
                 if verbose {
                     println!("[{}]: {}", "failed".red(), "not found in config".red());
-                } else {
-                    eprintln!("Map {} not found in config", map.blue());
                 }
+                report.error(DiagnosticKind::MissingInConfig(map.to_string()), None, None);
                 continue;
             }
 
             // pasrse map metadata
-            let map = match Self::from_conf_one(&toml[map], map.to_string(), verbose) {
-                Ok(map) => map,
-                Err(err) => {
-                    println!("[{}]: {}", "failed".red(), err.to_string().red());
+            if let Some(map) = Self::from_conf_reported(&toml[map], map, mode, verbose, report) {
+                maps.insert(map.name().clone(), map); // add to hashmap
+            }
+        }
+
+        // check for maps which are defined but not listed
+        if let Some(tables) = toml.as_table() {
+            for (name, table) in tables {
+                if name != "Maps"
+                    && table.get("path").is_some()
+                    && !maps_names.iter().any(|m| m.as_str() == Some(name))
+                {
+                    report.warn(DiagnosticKind::NotListed(name.clone()), None, None);
+                }
+            }
+        }
+
+        Ok(maps)
+    }
+
+    /// reads the maps of a config in the schema 0.2
+    ///
+    /// Every table in `[map]` is a map, tables with `enabled = false` are
+    /// skipped. Maps which cannot be read are skipped and recorded in `report`.
+    /// `mode` is used for every map which does not set its own `mode`.
+    pub fn from_conf_v2(
+        toml: &toml::Value,
+        mode: LoadMode,
+        verbose: bool,
+        report: &mut LoadReport,
+    ) -> Result<HashMap<String, Self>> {
+        let mut maps = HashMap::new();
+
+        let tables = match toml.get("map") {
+            Some(tables) => match tables.as_table() {
+                Some(tables) => tables,
+                None => return Err(Error::new_field_not_exists("map".to_string())),
+            },
+            // all maps can come from the directory
+            None if toml["Maps"].get("directory").is_some() => return Ok(maps),
+            None => return Err(Error::new_field_not_exists("map".to_string())),
+        };
+
+        for (name, table) in tables {
+            match table.get("enabled").map(|e| e.as_bool()) {
+                Some(Some(false)) => continue,
+                Some(None) => {
+                    report.error(
+                        DiagnosticKind::InvalidConfig(format!(
+                            "map.{}.enabled is not a bool",
+                            name
+                        )),
+                        None,
+                        None,
+                    );
                     continue;
                 }
-            };
+                _ => (),
+            }
 
             if verbose {
-                println!(
-                    "[{}]: Version: {}",
-                    "Ok".green(),
-                    map.version().to_string().blue()
-                );
+                print!("Loading infos of map {}... ", name.green());
+            }
+            if let Some(map) = Self::from_conf_reported(table, name, mode, verbose, report) {
+                maps.insert(map.name().clone(), map);
             }
-
-            maps.insert(map.name().clone(), map); // add to hashmap
         }
 
         Ok(maps)
     }
 
+    /// reads one map with `from_conf_one`, errors are recorded in `report`
+    fn from_conf_reported(
+        toml: &toml::Value,
+        name: &str,
+        mode: LoadMode,
+        verbose: bool,
+        report: &mut LoadReport,
+    ) -> Option<Self> {
+        let map = match Self::from_conf_one(toml, name.to_string(), mode, verbose) {
+            Ok(map) => map,
+            Err(err) => {
+                if verbose {
+                    println!("[{}]: {}", "failed".red(), err.to_string().red());
+                }
+                report.error(
+                    DiagnosticKind::InvalidConfig(format!("{}: {}", name, err)),
+                    None,
+                    None,
+                );
+                return None;
+            }
+        };
+
+        if verbose {
+            println!(
+                "[{}]: Version: {}",
+                "Ok".green(),
+                map.version().to_string().blue()
+            );
+        }
+        Some(map)
+    }
+
     /// reads one map from the toml and and returns the Map
     ///
     /// # Parameters
     /// `toml` - The toml Value of the given Map
     /// `name` - Name of the map to read
+    /// `mode` - load mode to use if the map does not set one
     /// `verbose` - set to true for debug output
-    fn from_conf_one(toml: &toml::Value, name: String, verbose: bool) -> Result<Self> {
-        let file = match toml.get("path") {
-            Some(path) => path,
-            None => return Err(Error::new_field_not_exists(format!("{}.path", name))),
-        };
-        let file = match file.as_str() {
-            Some(file) => file.to_string(),
-            None => return Err(Error::new_field_not_exists(format!("{}.path", name))),
-        };
-
+    fn from_conf_one(
+        toml: &toml::Value,
+        name: String,
+        mode: LoadMode,
+        verbose: bool,
+    ) -> Result<Self> {
         let version = match toml.get("version") {
             Some(version) => version,
             None => return Err(Error::new_field_not_exists(format!("{}.version", name))),
@@ -319,10 +723,7 @@ impl MapInfo {
         };
 
         let format_str = match toml.get("format") {
-            Some(format_str) => match format_str.as_str() {
-                Some(format) => format,
-                None => "JSON",
-            },
+            Some(format_str) => format_str.as_str().unwrap_or("JSON"),
             None => "JSON",
         };
 
@@ -362,15 +763,42 @@ impl MapInfo {
             None => None,
         };
 
-        let format: MapFormat;
-
-        if format_str.to_lowercase() == "json" {
-            format = MapFormat::JSON;
-        } else {
-            return Err(Error::new(super::error::ErrorKind::FormatNotSupported));
+        let mut format = MapFormat::from_str(format_str)?;
+        match &mut format {
+            MapFormat::Tiled(settings) => {
+                *settings = format::tiled::TiledSettings::from_conf(toml, &name)?
+            }
+            MapFormat::Random(settings) => {
+                *settings = generator::GeneratorSettings::from_conf(toml, &name)?
+            }
+            _ => (),
         }
 
-        Ok(Self::new(name, file, version, format, author, verbose))
+        // generated maps have no file
+        let file = match toml.get("path") {
+            Some(path) => match path.as_str() {
+                Some(file) => file.to_string(),
+                None => return Err(Error::new_field_not_exists(format!("{}.path", name))),
+            },
+            None => match format {
+                MapFormat::Random(_) => String::new(),
+                _ => return Err(Error::new_field_not_exists(format!("{}.path", name))),
+            },
+        };
+
+        let mode = match toml.get("mode") {
+            Some(mode) => match mode.as_str() {
+                Some(mode) => LoadMode::from_str(mode)?,
+                None => return Err(Error::new_field_not_exists(format!("{}.mode", name))),
+            },
+            None => mode,
+        };
+
+        let meta = MapMeta::from_value(toml, &name)?;
+
+        let mut map = Self::new(name, file, version, format, author, verbose);
+        map.set_mode(mode).set_meta(meta);
+        Ok(map)
     }
 
     /// returns the name of the Map it hold information about
@@ -396,6 +824,29 @@ impl MapInfo {
         self.p_verbose
     }
 
+    /// sets the checks to run while loading the map
+    pub fn set_mode(&mut self, mode: LoadMode) -> &mut Self {
+        self.p_mode = mode;
+        self
+    }
+
+    /// returns the load mode of the map
+    #[allow(dead_code)]
+    pub fn mode(&self) -> LoadMode {
+        self.p_mode
+    }
+
+    /// sets the metadata of the map, keys set here win over the map file
+    pub fn set_meta(&mut self, meta: MapMeta) -> &mut Self {
+        self.p_meta = meta;
+        self
+    }
+
+    /// returns the metadata set in the config
+    pub fn meta(&self) -> &MapMeta {
+        &self.p_meta
+    }
+
     /// returns the author of the map
     pub fn author(&self) -> Option<String> {
         let mut ret = String::new();
@@ -417,6 +868,27 @@ impl MapInfo {
 
     /// load and return a map
     pub fn load_map(&self) -> Result<Map> {
+        self.load_map_with_report().0
+    }
+
+    /// load a map and return it together with the report of the loader
+    ///
+    /// The report holds every problem the loader fixed on its own (like padded
+    /// cells or a differing name). If loading fails the error is also added to
+    /// the report, so the report is always complete.
+    pub fn load_map_with_report(&self) -> (Result<Map>, LoadReport) {
+        self.load_map_seeded(None)
+    }
+
+    /// like `load_map_with_report`, with the seed for generated maps
+    ///
+    /// Generated maps use the seed from the config if `seed` is `None` and
+    /// are named after the map and the seed, like `random 42`. See
+    /// `generator::seed_from_str` for valid seeds. The seed is ignored for
+    /// maps loaded from a file.
+    pub fn load_map_seeded(&self, seed: Option<&str>) -> (Result<Map>, LoadReport) {
+        let mut report = LoadReport::new(self.p_name.clone());
+
         if self.p_verbose {
             print!(
                 "Loading {} from {}...  ",
@@ -425,120 +897,196 @@ impl MapInfo {
             );
         }
 
-        // read json
-        let file = fs::read_to_string(&self.p_file)?;
-        let content: Value = serde_json::from_str(file.as_str())?;
-
-        // get name
-        let name: String = match content.get("name") {
-            Some(name) => match name.as_str() {
-                Some(name) => name.to_string(),
-                None => return Err(Error::new_field_not_exists("name".to_string())),
-            },
-            None => return Err(Error::new_field_not_exists("name".to_string())),
+        let map = match &self.p_format {
+            MapFormat::Random(settings) => {
+                let name = match seed {
+                    Some(seed) => format!("{} {}", self.p_name, seed),
+                    None => format!("{} {}", self.p_name, settings.seed),
+                };
+                let seed = seed.map_or(settings.seed, generator::seed_from_str);
+                let mut map = generator::generate(&name, settings, seed);
+                map.p_meta = self.p_meta.clone();
+                Ok(map)
+            }
+            _ => fs::read_to_string(&self.p_file)
+                .map_err(Error::from)
+                .and_then(|content| self.parse_map(&content, &mut report)),
         };
-        if name != self.p_name {
-            // check name
-            eprintln!("Map name {} differs from name {}", name, self.p_name);
-        }
 
-        // get feature list
-        let mut features: Option<Vec<String>> = None;
-        let f = content.get("features");
-        if f.is_some() {
-            let f = f.unwrap();
-            if f.is_array() {
-                let mut fe = Vec::new();
-                for v in f.as_array().unwrap() {
-                    if !v.is_str() {
-                        return Err(Error::new_field_not_exists(
-                            "features is not string".to_string(),
-                        ));
-                    }
-                    fe.push(v.as_str().unwrap().to_string());
+        match &map {
+            Ok(map) => {
+                if self.p_verbose {
+                    println!(
+                        "[{}]: Loaded Map {} with size {}",
+                        "Ok".green(),
+                        map.p_name.blue(),
+                        map.size().yellow()
+                    )
+                }
+            }
+            Err(err) => {
+                if self.p_verbose {
+                    println!("[{}]: {}", "failed".red(), err.to_string().red());
                 }
-                features = Some(fe); // attach to fe
-            } else if f.is_str() {
-                features = Some(vec![f.as_str().unwrap().to_string()]);
+                report.error(
+                    DiagnosticKind::Failed(format!("{}: {}", self.p_file, err)),
+                    None,
+                    None,
+                );
             }
         }
-        drop(f); // remove f
-        features = match features {
-            // check if it only says none
-            Some(f) => {
-                let mut ret: Option<Vec<String>> = Some(f.clone());
-                if f.len() == 1 {
-                    if f[0] == "none" {
-                        ret = None;
-                    }
+
+        (map, report)
+    }
+
+    /// parse the content of a map file
+    ///
+    /// Every problem which can be fixed is fixed and recorded in `report`.
+    /// In strict mode these problems are recorded as errors and the map is rejected
+    /// after all of them are collected.
+    fn parse_map(&self, content: &str, report: &mut LoadReport) -> Result<Map> {
+        let raw = self.p_format.loader()?.parse(content)?;
+
+        // check name
+        let name = match raw.name {
+            Some(name) => {
+                if name != self.p_name {
+                    self.problem(
+                        report,
+                        DiagnosticKind::NameMismatch {
+                            expected: self.p_name.clone(),
+                            found: name.clone(),
+                        },
+                        None,
+                        None,
+                    );
                 }
-                ret
+                name
             }
-            None => None,
+            None => self.p_name.clone(),
         };
 
-        // load map
+        // check if it only says none
+        let mut features = raw.features;
+        if let Some(f) = &features {
+            if f.len() == 1 && f[0] == "none" {
+                features = None;
+            }
+        }
+
         let mut map: Vec<[u8; WIDTH]> = Vec::new();
-        let j_map = match content.get("map") {
-            Some(j) => j,
-            None => return Err(Error::new_field_not_exists("map".to_string())),
-        };
-        let j_map = match j_map.as_array() {
-            Some(j) => j,
-            None => return Err(Error::new_field_not_exists("map".to_string())),
-        };
-        let mut user_warned = false;
-        for v in j_map {
-            let v = match v.as_array() {
-                Some(j) => j,
-                None => return Err(Error::new_field_not_exists("map".to_string())),
-            };
-            if !user_warned {
-                if v.len() < WIDTH {
-                    eprintln!("\nmap smaller than {} collums", WIDTH);
-                    user_warned = true;
-                } else if v.len() > WIDTH {
-                    eprintln!("\nmap to big");
-                    user_warned = true;
-                }
+        for (y, v) in raw.rows.iter().enumerate() {
+            if v.len() < WIDTH {
+                self.problem(
+                    report,
+                    DiagnosticKind::PaddedCells(WIDTH - v.len()),
+                    Some(y),
+                    Some(v.len()),
+                );
+            } else if v.len() > WIDTH {
+                self.problem(
+                    report,
+                    DiagnosticKind::CroppedCells(v.len() - WIDTH),
+                    Some(y),
+                    Some(WIDTH),
+                );
             }
-            let mut row: [u8; WIDTH] = [0; WIDTH];
-            for i in 0..WIDTH {
-                let b = match v.get(i) {
-                    Some(b) => b.as_integer(),
-                    None => None,
-                };
-                let b = match b {
-                    Some(b) => b as u8,
-                    None => 1 as u8, // return solid block
-                };
-                row[i] = b;
+            let mut row: [u8; WIDTH] = [1; WIDTH]; // missing cells are solid blocks
+            for (x, (cell, b)) in row.iter_mut().zip(v.iter()).enumerate() {
+                match *b {
+                    Cell::Code(b) if (0..=i64::from(u8::MAX)).contains(&b) => {
+                        *cell = b as u8;
+                        if self.p_mode == LoadMode::Strict && *cell > MAX_TILE {
+                            report.error(DiagnosticKind::UnknownTile(*cell), Some(y), Some(x));
+                        }
+                    }
+                    Cell::Code(b) => {
+                        self.problem(report, DiagnosticKind::OutOfRange(b), Some(y), Some(x))
+                    }
+                    Cell::Invalid => {
+                        self.problem(report, DiagnosticKind::NotNumeric, Some(y), Some(x))
+                    }
+                    Cell::Unmapped(id) => {
+                        self.problem(report, DiagnosticKind::UnmappedTile(id), Some(y), Some(x))
+                    }
+                }
             }
             map.push(row);
         }
-        drop(j_map); // remove j_map
 
-        let ret = Map {
+        if self.p_mode == LoadMode::Strict && report.has_errors() {
+            return Err(Error::new(super::error::ErrorKind::MapNotValid(
+                self.p_name.clone(),
+            )));
+        }
+
+        Ok(Map {
             p_name: name,
             p_features: features,
+            p_meta: self.p_meta.clone().or(raw.meta),
+            p_analysis: Analysis::new(&map),
             p_map: map,
-        };
+        })
+    }
 
-        if self.p_verbose {
-            println!(
-                "[{}]: Loaded Map {} with size {}",
-                "Ok".green(),
-                ret.p_name.blue(),
-                ret.size().yellow()
-            )
+    /// records a problem the loader can fix on its own
+    ///
+    /// The problem is a warning in lenient mode and an error in strict mode.
+    fn problem(
+        &self,
+        report: &mut LoadReport,
+        kind: DiagnosticKind,
+        row: Option<usize>,
+        column: Option<usize>,
+    ) {
+        match self.p_mode {
+            LoadMode::Lenient => report.warn(kind, row, column),
+            LoadMode::Strict => report.error(kind, row, column),
         }
-
-        Ok(ret)
     }
 }
 
-/// map format is a enum of possible loader elements for a map file
-pub enum MapFormat {
-    /// JSON as the javascript object notation
-    JSON,
+/// load mode decides which problems in a map file are fatal
+///
+/// The mode is set for all maps with `mode` in the `[Maps]` table of the config,
+/// and can be overwritten per map with `mode` in the table of the map.
+/// Valid values are `"lenient"` (default) and `"strict"`.
+///
+/// # Checks
+/// | Check                             | Lenient                 | Strict |
+/// |-----------------------------------|-------------------------|--------|
+/// | row smaller than `WIDTH`          | warning, padded with 1  | error  |
+/// | row bigger than `WIDTH`           | warning, cropped        | error  |
+/// | name differs from config          | warning                 | error  |
+/// | cell is not a number              | warning, replaced by 1  | error  |
+/// | cell does not fit into a `u8`     | warning, replaced by 1  | error  |
+/// | tile without mapping (Tiled)      | warning, replaced by 1  | error  |
+/// | tile code bigger than `MAX_TILE`  | not checked             | error  |
+///
+/// In strict mode every check runs, all errors are collected into the
+/// `LoadReport` and the map is rejected with `ErrorKind::MapNotValid`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LoadMode {
+    /// fix problems and report them as warnings
+    #[default]
+    Lenient,
+
+    /// reject maps with problems
+    Strict,
+}
+
+impl FromStr for LoadMode {
+    type Err = Error;
+
+    /// parses the load mode case insensitive
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "lenient" => Ok(LoadMode::Lenient),
+            "strict" => Ok(LoadMode::Strict),
+            _ => Err(Error::new(super::error::ErrorKind::NotParsable(format!(
+                "unknown load mode {}",
+                s
+            )))),
+        }
+    }
 }
@@ -0,0 +1,201 @@
+use super::tile::Tile;
+use super::Map;
+use colored::*;
+
+/// glyph used for codes which are not in the table on `Map`
+pub const UNKNOWN_GLYPH: char = '?';
+
+/// draws the map with colors for the terminal
+///
+/// Every tile is drawn two characters wide, so the map keeps its proportions.
+/// The output starts with the name, size and features of the map, and ends
+/// with a legend of every tile used in the map.
+pub fn terminal(map: &Map) -> String {
+    let mut out = format!(
+        "{} ({}), features: {}\n",
+        map.name().green(),
+        map.size().yellow(),
+        match map.feature_list().as_str() {
+            "" => "none".to_string(),
+            list => list.to_string(),
+        }
+    );
+
+    let mut used: Vec<u8> = Vec::new();
+    for row in map.rows() {
+        for code in row.iter() {
+            out += &cell(*code).to_string();
+            if !used.contains(code) {
+                used.push(*code);
+            }
+        }
+        out += "\n";
+    }
+
+    used.sort();
+    out += "\n";
+    for code in used {
+        let description = match Tile::from_code(code) {
+            Some(tile) => tile.description(),
+            None => "unknown",
+        };
+        out += &format!("{} {:>2}: {}\n", cell(code), code, description);
+    }
+    out
+}
+
+/// returns the colored glyph of a single cell, two characters wide
+fn cell(code: u8) -> ColoredString {
+    match Tile::from_code(code) {
+        Some(Tile::Solid) => "██".white(),
+        Some(Tile::None) => "  ".normal(),
+        Some(tile) => format!("{}{}", tile.glyph(), tile.glyph())
+            .color(tile.color())
+            .bold(),
+        None => format!("{}{}", UNKNOWN_GLYPH, UNKNOWN_GLYPH)
+            .black()
+            .on_magenta(),
+    }
+}
+
+/// color used for codes which are not in the table on `Map`
+pub const UNKNOWN_RGB: (u8, u8, u8) = (0xff, 0x00, 0xff);
+
+/// returns the color of a single cell for images
+fn rgb(code: u8) -> (u8, u8, u8) {
+    Tile::from_code(code).map_or(UNKNOWN_RGB, Tile::rgb)
+}
+
+/// draws the map as svg image, every tile is a square of `scale` pixels
+///
+/// # Example
+/// ```
+/// use poke_escape_server::map::generator::{generate, GeneratorSettings};
+/// use poke_escape_server::map::render;
+/// let map = generate("random 1", &GeneratorSettings::default(), 1);
+/// assert!(render::svg(&map, 16).starts_with("<svg"));
+/// ```
+pub fn svg(map: &Map, scale: usize) -> String {
+    let rows = map.rows();
+    let width = rows.first().map_or(0, |r| r.len()) * scale;
+    let height = rows.len() * scale;
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n",
+        width, height, width, height
+    );
+    out += &format!("<title>{}</title>\n", escape(&map.name()));
+    for (y, row) in rows.iter().enumerate() {
+        for (x, code) in row.iter().enumerate() {
+            let (r, g, b) = rgb(*code);
+            out += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#{:02x}{:02x}{:02x}\"/>\n",
+                x * scale,
+                y * scale,
+                scale,
+                scale,
+                r,
+                g,
+                b
+            );
+        }
+    }
+    out += "</svg>\n";
+    out
+}
+
+/// escapes text for xml
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// draws the map as png image, every tile is a square of `scale` pixels
+///
+/// The image is written without compression, so no extra crate is needed.
+/// Thumbnails of maps are small enough for this.
+pub fn png(map: &Map, scale: usize) -> Vec<u8> {
+    let rows = map.rows();
+    let width = rows.first().map_or(0, |r| r.len()) * scale;
+    let height = rows.len() * scale;
+
+    // every scanline starts with the filter type 0 (none)
+    let mut raw = Vec::with_capacity((width * 3 + 1) * height);
+    for row in rows {
+        let mut line = Vec::with_capacity(width * 3 + 1);
+        line.push(0);
+        for code in row.iter() {
+            let (r, g, b) = rgb(*code);
+            for _ in 0..scale {
+                line.extend_from_slice(&[r, g, b]);
+            }
+        }
+        for _ in 0..scale {
+            raw.extend_from_slice(&line);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bit rgb, no interlace
+
+    let mut out = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// appends a png chunk with length and checksum
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// wraps the data into a zlib stream of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(0xffff).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// checksum of png chunks
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// checksum of zlib streams
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + u32::from(*byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,312 @@
+use colored::*;
+use serde_derive::Serialize;
+use std::fmt;
+
+/// severity of a single `Diagnostic`
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum Level {
+    /// the loader fixed the problem on its own, the map is still usable
+    Warning,
+
+    /// the problem could not be fixed, the map (or config entry) was rejected
+    Error,
+}
+
+impl fmt::Display for Level {
+    /// standart formater for print! macro
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Warning => write!(f, "warning"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// describes what a `Diagnostic` is about
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum DiagnosticKind {
+    /// map is listed in `Maps.maps`, but there is no table for it in the config
+    /// holds the name of the map
+    MissingInConfig(String),
+
+    /// entry in the config could not be read, holds the reason
+    InvalidConfig(String),
+
+    /// server setting or environment variable is unknown and was ignored
+    /// holds its name
+    UnknownSetting(String),
+
+    /// name inside the map file differs from the name used in the config
+    NameMismatch {
+        /// name used in the config
+        expected: String,
+
+        /// name found in the map file
+        found: String,
+    },
+
+    /// row is smaller than `WIDTH`, holds the number of cells padded with a solid block
+    PaddedCells(usize),
+
+    /// row is bigger than `WIDTH`, holds the number of cells cropped on the right side
+    CroppedCells(usize),
+
+    /// cell is not a number, it was replaced by a solid block
+    NotNumeric,
+
+    /// cell does not fit into a tile code, it was replaced by a solid block
+    /// holds the value found
+    OutOfRange(i64),
+
+    /// tile of an imported map has no mapping to a tile code, it was replaced by a
+    /// solid block, holds the id of the tile
+    UnmappedTile(u32),
+
+    /// tile code is not described in the table on `Map`, holds the code
+    UnknownTile(u8),
+
+    /// table in the config looks like a map, but is not listed in `Maps.maps`
+    /// holds the name of the table
+    NotListed(String),
+
+    /// map file found in `Maps.directory` was skipped, as its name is already used
+    Conflict {
+        /// name of the map
+        name: String,
+
+        /// path of the skipped file
+        path: String,
+    },
+
+    /// map has no start point (5)
+    NoStart,
+
+    /// map has no exit, every cell on the border is a solid block
+    NoExit,
+
+    /// start point has no path to the exit
+    StuckStart,
+
+    /// no start point has a path to the berry
+    UnreachableBerry,
+
+    /// information lost while writing a map into another format, holds what was lost
+    Lossy(String),
+
+    /// loading failed, holds the description of the error
+    Failed(String),
+}
+
+impl fmt::Display for DiagnosticKind {
+    /// standart formater for print! macro
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::MissingInConfig(name) => write!(f, "map {} not found in config", name),
+            DiagnosticKind::InvalidConfig(reason) => write!(f, "invalid config: {}", reason),
+            DiagnosticKind::UnknownSetting(name) => write!(f, "ignoring unknown setting {}", name),
+            DiagnosticKind::NameMismatch { expected, found } => {
+                write!(f, "map name {} differs from name {}", found, expected)
+            }
+            DiagnosticKind::PaddedCells(count) => {
+                write!(
+                    f,
+                    "row smaller than {}, padded {} cells",
+                    super::WIDTH,
+                    count
+                )
+            }
+            DiagnosticKind::CroppedCells(count) => {
+                write!(
+                    f,
+                    "row bigger than {}, cropped {} cells",
+                    super::WIDTH,
+                    count
+                )
+            }
+            DiagnosticKind::NotNumeric => write!(f, "cell is not a number"),
+            DiagnosticKind::OutOfRange(value) => write!(f, "cell value {} out of range", value),
+            DiagnosticKind::UnmappedTile(id) => write!(f, "tile {} has no mapping", id),
+            DiagnosticKind::UnknownTile(code) => write!(f, "unknown tile code {}", code),
+            DiagnosticKind::NotListed(name) => {
+                write!(f, "map {} is defined but not listed in Maps.maps", name)
+            }
+            DiagnosticKind::Conflict { name, path } => {
+                write!(
+                    f,
+                    "map {} in {} skipped, the name is already used",
+                    name, path
+                )
+            }
+            DiagnosticKind::NoStart => write!(f, "map has no start point"),
+            DiagnosticKind::NoExit => write!(f, "map has no exit"),
+            DiagnosticKind::StuckStart => write!(f, "start point cannot reach the exit"),
+            DiagnosticKind::UnreachableBerry => write!(f, "berry cannot be reached"),
+            DiagnosticKind::Lossy(reason) => write!(f, "lossy conversion: {}", reason),
+            DiagnosticKind::Failed(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// a single warning or error found while loading the config or a map
+///
+/// `row` and `column` are zero based and point into the map grid as written
+/// in the map file. They are `None` if the diagnostic is not about a cell.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// severity of the diagnostic
+    pub level: Level,
+
+    /// what the diagnostic is about
+    pub kind: DiagnosticKind,
+
+    /// row in the map grid
+    pub row: Option<usize>,
+
+    /// column in the map grid
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    /// create a new instance of Diagnostic
+    pub fn new(
+        level: Level,
+        kind: DiagnosticKind,
+        row: Option<usize>,
+        column: Option<usize>,
+    ) -> Self {
+        Diagnostic {
+            level,
+            kind,
+            row,
+            column,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// returns level and position of the diagnostic, colored by level
+    pub fn colored_position(&self) -> String {
+        let level = match self.level {
+            Level::Warning => self.level.to_string().yellow(),
+            Level::Error => self.level.to_string().red(),
+        };
+        match (self.row, self.column) {
+            (Some(row), Some(column)) => format!("{} (row {}, column {})", level, row, column),
+            (Some(row), None) => format!("{} (row {})", level, row),
+            _ => level.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// standart formater for print! macro
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.row, self.column) {
+            (Some(row), Some(column)) => {
+                write!(
+                    f,
+                    "{} (row {}, column {}): {}",
+                    self.level, row, column, self.kind
+                )
+            }
+            (Some(row), None) => write!(f, "{} (row {}): {}", self.level, row, self.kind),
+            _ => write!(f, "{}: {}", self.level, self.kind),
+        }
+    }
+}
+
+/// LoadReport collects every `Diagnostic` raised while loading a config or a map
+///
+/// # Example
+/// ```
+/// use poke_escape_server::map::report::{DiagnosticKind, LoadReport};
+/// let mut report = LoadReport::new("Hello World".to_string());
+/// report.warn(DiagnosticKind::NotNumeric, Some(3), Some(7));
+/// assert_eq!(report.warnings().count(), 1);
+/// assert!(!report.has_errors());
+/// ```
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LoadReport {
+    /// name of the map or path of the config the report is about
+    p_source: String,
+
+    /// diagnostics in the order they were found
+    p_entries: Vec<Diagnostic>,
+}
+
+impl LoadReport {
+    /// create a new empty report for the given map name or config path
+    pub fn new(source: String) -> Self {
+        LoadReport {
+            p_source: source,
+            p_entries: Vec::new(),
+        }
+    }
+
+    /// returns the map name or config path the report is about
+    pub fn source(&self) -> &str {
+        &self.p_source
+    }
+
+    /// add a diagnostic to the report
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.p_entries.push(diagnostic);
+    }
+
+    /// add a warning to the report
+    pub fn warn(&mut self, kind: DiagnosticKind, row: Option<usize>, column: Option<usize>) {
+        self.push(Diagnostic::new(Level::Warning, kind, row, column));
+    }
+
+    /// add an error to the report
+    pub fn error(&mut self, kind: DiagnosticKind, row: Option<usize>, column: Option<usize>) {
+        self.push(Diagnostic::new(Level::Error, kind, row, column));
+    }
+
+    /// returns all diagnostics of the report
+    pub fn entries(&self) -> &[Diagnostic] {
+        &self.p_entries
+    }
+
+    /// returns all warnings of the report
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.p_entries.iter().filter(|d| d.level == Level::Warning)
+    }
+
+    /// returns all errors of the report
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.p_entries.iter().filter(|d| d.level == Level::Error)
+    }
+
+    /// checks if the report contains at least one error
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// checks if nothing was reported
+    pub fn is_empty(&self) -> bool {
+        self.p_entries.is_empty()
+    }
+
+    /// prints all diagnostics with colors to stderr
+    pub fn print(&self) {
+        for entry in &self.p_entries {
+            eprintln!(
+                "{} in {}: {}",
+                entry.colored_position(),
+                self.p_source.blue(),
+                entry.kind
+            );
+        }
+    }
+}
+
+impl fmt::Display for LoadReport {
+    /// standart formater for print! macro, one diagnostic per line
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.p_entries {
+            writeln!(f, "{}: {}", self.p_source, entry)?;
+        }
+        Ok(())
+    }
+}
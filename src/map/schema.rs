@@ -0,0 +1,196 @@
+use super::report::{DiagnosticKind, LoadReport};
+use super::{LoadMode, MapInfo};
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
+use std::fmt;
+use toml::value::Table;
+use toml::Value;
+
+use super::super::error::{Error, ErrorKind};
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// ConfigSchema is a layout of config.toml, selected with `Maps.version`
+///
+/// # Schemas
+/// `0.1.x` lists the maps in `Maps.maps`, every map is a top level table:
+/// ```toml
+/// [Maps]
+/// version = "0.1.0"
+/// maps = ["Hello World"]
+///
+/// ["Hello World"]
+/// version = "0.1.0"
+/// path = "./maps/Hello_World.map"
+/// ```
+/// `0.2.x` has no list, every table in `[map]` is a map. A map is skipped
+/// with `enabled = false`:
+/// ```toml
+/// [Maps]
+/// version = "0.2.0"
+///
+/// [map."Hello World"]
+/// version = "0.1.0"
+/// path = "./maps/Hello_World.map"
+/// ```
+/// Old configs are rewritten into the newest schema with `migrate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigSchema {
+    /// maps listed in `Maps.maps`, tables at the top level
+    V1,
+
+    /// maps in the `[map]` table, with optional `enabled`
+    V2,
+}
+
+impl ConfigSchema {
+    /// all schemas, oldest first
+    pub const ALL: [ConfigSchema; 2] = [ConfigSchema::V1, ConfigSchema::V2];
+
+    /// schema written by `migrate`
+    pub const LATEST: ConfigSchema = ConfigSchema::V2;
+
+    /// returns the range of `Maps.version` read with this schema
+    pub fn requirement(self) -> VersionReq {
+        let req = match self {
+            ConfigSchema::V1 => ">= 0.1.0, < 0.2.0",
+            ConfigSchema::V2 => ">= 0.2.0, < 0.3.0",
+        };
+        VersionReq::parse(req).expect("valid version requirement")
+    }
+
+    /// returns the version written into `Maps.version`
+    pub fn version(self) -> Version {
+        match self {
+            ConfigSchema::V1 => Version::new(0, 1, 0),
+            ConfigSchema::V2 => Version::new(0, 2, 0),
+        }
+    }
+
+    /// returns the schema reading configs of the version
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::map::schema::ConfigSchema;
+    /// use semver::Version;
+    /// let schema = ConfigSchema::for_version(&Version::new(0, 1, 3)).unwrap();
+    /// assert_eq!(schema, ConfigSchema::V1);
+    /// assert!(ConfigSchema::for_version(&Version::new(1, 0, 0)).is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `VersionNotSupported` with the supported ranges if no schema matches.
+    pub fn for_version(version: &Version) -> Result<Self> {
+        match Self::ALL.iter().find(|s| s.requirement().matches(version)) {
+            Some(schema) => Ok(*schema),
+            None => {
+                let supported: Vec<String> = Self::ALL
+                    .iter()
+                    .map(|s| s.requirement().to_string())
+                    .collect();
+                Err(Error::new(ErrorKind::VersionNotSupported(format!(
+                    "Maps.version {}, supported: {}",
+                    version,
+                    supported.join("; ")
+                ))))
+            }
+        }
+    }
+
+    /// reads the maps of the config with this schema
+    pub fn load(
+        self,
+        content: &Value,
+        mode: LoadMode,
+        verbose: bool,
+        report: &mut LoadReport,
+    ) -> Result<HashMap<String, MapInfo>> {
+        match self {
+            ConfigSchema::V1 => MapInfo::from_conf(content, mode, verbose, report),
+            ConfigSchema::V2 => MapInfo::from_conf_v2(content, mode, verbose, report),
+        }
+    }
+}
+
+impl fmt::Display for ConfigSchema {
+    /// standart formater for print! macro
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.requirement())
+    }
+}
+
+/// rewrites a config into the newest schema
+///
+/// Maps listed in `Maps.maps` are moved into `[map]`. Tables with a `path`
+/// which are not listed are moved too, with `enabled = false`, so they stay
+/// disabled. Listed names without a table are dropped. Every change which
+/// is not a plain move is recorded in `report`. Comments are not kept.
+///
+/// # Errors
+/// Returns an error if `Maps.version` is missing or not supported.
+pub fn migrate(content: &Value, report: &mut LoadReport) -> Result<Value> {
+    let version = match content.get("Maps").and_then(|m| m.get("version")) {
+        Some(version) => match version.as_str() {
+            Some(version) => Version::parse(version)?,
+            None => return Err(Error::new_field_not_exists("Maps.version".to_string())),
+        },
+        None => return Err(Error::new_field_not_exists("Maps.version".to_string())),
+    };
+
+    match ConfigSchema::for_version(&version)? {
+        ConfigSchema::V1 => Ok(migrate_v1(content, report)),
+        ConfigSchema::V2 => Ok(content.clone()),
+    }
+}
+
+/// rewrites a `0.1.x` config into `0.2.0`
+fn migrate_v1(content: &Value, report: &mut LoadReport) -> Value {
+    let empty = Table::new();
+    let tables = content.as_table().unwrap_or(&empty);
+    let listed: Vec<&str> = content["Maps"]
+        .get("maps")
+        .and_then(|m| m.as_array())
+        .map(|m| m.iter().filter_map(|m| m.as_str()).collect())
+        .unwrap_or_default();
+
+    let mut out = Table::new();
+    let mut maps = Table::new();
+
+    for name in &listed {
+        match tables.get(*name) {
+            Some(table) => {
+                maps.insert(name.to_string(), table.clone());
+            }
+            None => report.warn(
+                DiagnosticKind::MissingInConfig(name.to_string()),
+                None,
+                None,
+            ),
+        }
+    }
+
+    for (name, table) in tables {
+        if name == "Maps" {
+            let mut settings = table.as_table().cloned().unwrap_or_default();
+            settings.remove("maps");
+            settings.insert(
+                "version".to_string(),
+                Value::String(ConfigSchema::LATEST.version().to_string()),
+            );
+            out.insert(name.clone(), Value::Table(settings));
+        } else if listed.contains(&name.as_str()) {
+            continue;
+        } else if table.get("path").is_some() {
+            let mut table = table.as_table().cloned().unwrap_or_default();
+            table.insert("enabled".to_string(), Value::Boolean(false));
+            maps.insert(name.clone(), Value::Table(table));
+            report.warn(DiagnosticKind::NotListed(name.clone()), None, None);
+        } else {
+            out.insert(name.clone(), table.clone());
+        }
+    }
+
+    out.insert("map".to_string(), Value::Table(maps));
+    Value::Table(out)
+}
@@ -0,0 +1,137 @@
+use super::MapPlaces;
+use colored::*;
+use std::fs;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// MapStore holds the `MapPlaces` of the running server and reloads them
+///
+/// Readers take a snapshot with `current`. A reload builds a complete new
+/// `MapPlaces` and swaps it in at once, so requests already holding a
+/// snapshot finish with the old maps. If the new config cannot be loaded,
+/// the old maps stay in place.
+pub struct MapStore {
+    /// config file to load the maps from
+    p_file: String,
+
+    /// verbose state used while loading
+    p_verbose: bool,
+
+    /// maps currently served
+    p_current: RwLock<Arc<MapPlaces>>,
+}
+
+impl MapStore {
+    /// loads the maps from the config file
+    ///
+    /// # Arguments
+    /// - `file` -> give the path of the toml file to load
+    /// - `verbose` -> if set true shows status of loading
+    pub fn new(file: &str, verbose: bool) -> Result<Self> {
+        let maps = MapPlaces::new(file, verbose)?;
+        Ok(Self::from_places(file, verbose, maps))
+    }
+
+    /// creates the store with already loaded maps
+    pub fn from_places(file: &str, verbose: bool, maps: MapPlaces) -> Self {
+        MapStore {
+            p_file: file.to_string(),
+            p_verbose: verbose,
+            p_current: RwLock::new(Arc::new(maps)),
+        }
+    }
+
+    /// returns the maps currently served
+    pub fn current(&self) -> Arc<MapPlaces> {
+        match self.p_current.read() {
+            Ok(current) => Arc::clone(&current),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    /// returns the config file the maps are loaded from
+    pub fn file(&self) -> &str {
+        &self.p_file
+    }
+
+    /// loads the config file again and swaps the maps
+    ///
+    /// # Errors
+    /// Returns the error of `MapPlaces::new`, the old maps are kept in this case.
+    pub fn reload(&self) -> Result<Arc<MapPlaces>> {
+        let maps = Arc::new(MapPlaces::new(&self.p_file, self.p_verbose)?);
+        match self.p_current.write() {
+            Ok(mut current) => *current = Arc::clone(&maps),
+            Err(poisoned) => *poisoned.into_inner() = Arc::clone(&maps),
+        }
+        Ok(maps)
+    }
+
+    /// reloads the maps and prints the result, used by the reload triggers
+    pub fn reload_and_print(&self, trigger: &str) {
+        println!("Reloading {} ({})", self.p_file.blue(), trigger);
+        match self.reload() {
+            Ok(maps) => {
+                maps.report().print();
+                println!(
+                    "[{}]: {} maps available",
+                    "Ok".green(),
+                    maps.available_maps().len().to_string().yellow()
+                );
+            }
+            Err(err) => eprintln!(
+                "[{}]: {}, keeping the old maps",
+                "failed".red(),
+                err.to_string().red()
+            ),
+        }
+    }
+
+    /// reloads the maps on SIGHUP
+    ///
+    /// Spawns a thread waiting for the signal.
+    #[cfg(unix)]
+    pub fn reload_on_sighup(store: Arc<MapStore>) -> Result<()> {
+        use signal_hook::consts::SIGHUP;
+        use signal_hook::iterator::Signals;
+
+        let mut signals = Signals::new([SIGHUP])?;
+        std::thread::spawn(move || {
+            for _ in signals.forever() {
+                store.reload_and_print("SIGHUP");
+            }
+        });
+        Ok(())
+    }
+
+    /// reloads the maps when the config file changes
+    ///
+    /// Spawns a thread checking the modification time of the config file, and
+    /// of `Maps.directory` if it is set, every `interval`.
+    pub fn watch(store: Arc<MapStore>, interval: Duration) {
+        std::thread::spawn(move || {
+            let mut last = store.modified();
+            loop {
+                std::thread::sleep(interval);
+                let modified = store.modified();
+                if modified != last {
+                    last = modified;
+                    store.reload_and_print("file changed");
+                }
+            }
+        });
+    }
+
+    /// returns the modification times of the config file and the map directory
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        let mtime = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut times = vec![mtime(&self.p_file)];
+        if let Some(directory) = self.current().directory() {
+            times.push(mtime(directory));
+        }
+        times
+    }
+}
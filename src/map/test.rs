@@ -0,0 +1,978 @@
+//! test file to test the map loader
+
+mod load_report {
+    use super::super::report::{DiagnosticKind, Level, LoadReport};
+    use super::super::{MapFormat, MapInfo, WIDTH};
+    use semver::Version;
+
+    fn info(name: &str) -> MapInfo {
+        MapInfo::new(
+            name.to_string(),
+            "./maps/test.map".to_string(),
+            Version::new(0, 1, 0),
+            MapFormat::JSON,
+            None,
+            false,
+        )
+    }
+
+    #[test]
+    fn clean_map_has_empty_report() {
+        let mut report = LoadReport::new("Test".to_string());
+        let row = vec!["0"; WIDTH].join(",");
+        let content = format!(r#"{{"name": "Test", "map": [[{}]]}}"#, row);
+        let map = info("Test").parse_map(&content, &mut report).unwrap();
+        assert_eq!(map.size(), format!("{}x1", WIDTH));
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn name_mismatch() {
+        let mut report = LoadReport::new("Test".to_string());
+        let content = r#"{"name": "Other", "map": []}"#;
+        info("Test").parse_map(content, &mut report).unwrap();
+        assert_eq!(
+            report.entries()[0].kind,
+            DiagnosticKind::NameMismatch {
+                expected: "Test".to_string(),
+                found: "Other".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn padded_and_cropped_rows() {
+        let mut report = LoadReport::new("Test".to_string());
+        let long = vec!["0"; WIDTH + 2].join(",");
+        let content = format!(r#"{{"name": "Test", "map": [[0, 0, 0], [{}]]}}"#, long);
+        let map = info("Test").parse_map(&content, &mut report).unwrap();
+        assert_eq!(map.p_map[0][3], 1);
+        let entries = report.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, DiagnosticKind::PaddedCells(WIDTH - 3));
+        assert_eq!((entries[0].row, entries[0].column), (Some(0), Some(3)));
+        assert_eq!(entries[1].kind, DiagnosticKind::CroppedCells(2));
+        assert_eq!((entries[1].row, entries[1].column), (Some(1), Some(WIDTH)));
+    }
+
+    #[test]
+    fn invalid_cells() {
+        let mut report = LoadReport::new("Test".to_string());
+        let content = r#"{"name": "Test", "map": [[0, "a", 300]]}"#;
+        let map = info("Test").parse_map(content, &mut report).unwrap();
+        assert_eq!(&map.p_map[0][..3], &[0, 1, 1]);
+        let cells: Vec<_> = report
+            .warnings()
+            .filter(|d| d.kind != DiagnosticKind::PaddedCells(WIDTH - 3))
+            .map(|d| (d.kind.clone(), d.column))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (DiagnosticKind::NotNumeric, Some(1)),
+                (DiagnosticKind::OutOfRange(300), Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_load_is_reported() {
+        let (map, report) = info("Test").load_map_with_report();
+        assert!(map.is_err());
+        assert!(report.has_errors());
+        assert_eq!(report.entries()[0].level, Level::Error);
+    }
+}
+
+mod load_mode {
+    use super::super::report::{DiagnosticKind, LoadReport};
+    use super::super::{LoadMode, MapFormat, MapInfo, WIDTH};
+    use semver::Version;
+    use std::str::FromStr;
+
+    fn info(mode: LoadMode) -> MapInfo {
+        let mut info = MapInfo::new(
+            "Test".to_string(),
+            "./maps/test.map".to_string(),
+            Version::new(0, 1, 0),
+            MapFormat::JSON,
+            None,
+            false,
+        );
+        info.set_mode(mode);
+        info
+    }
+
+    #[test]
+    fn parse_mode() {
+        assert_eq!(LoadMode::from_str("Strict").unwrap(), LoadMode::Strict);
+        assert_eq!(LoadMode::from_str("lenient").unwrap(), LoadMode::Lenient);
+        assert!(LoadMode::from_str("lazy").is_err());
+    }
+
+    #[test]
+    fn strict_accepts_clean_map() {
+        let mut report = LoadReport::new("Test".to_string());
+        let row = vec!["12"; WIDTH].join(",");
+        let content = format!(r#"{{"name": "Test", "map": [[{}]]}}"#, row);
+        assert!(info(LoadMode::Strict)
+            .parse_map(&content, &mut report)
+            .is_ok());
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn strict_collects_all_errors() {
+        let mut report = LoadReport::new("Test".to_string());
+        let mut row = vec!["0"; WIDTH - 1];
+        row[2] = "\"a\"";
+        row[3] = "13";
+        let content = format!(r#"{{"name": "Other", "map": [[{}]]}}"#, row.join(","));
+        assert!(info(LoadMode::Strict)
+            .parse_map(&content, &mut report)
+            .is_err());
+        let kinds: Vec<_> = report.errors().map(|d| d.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::NameMismatch {
+                    expected: "Test".to_string(),
+                    found: "Other".to_string(),
+                },
+                DiagnosticKind::PaddedCells(1),
+                DiagnosticKind::NotNumeric,
+                DiagnosticKind::UnknownTile(13),
+            ]
+        );
+    }
+
+    #[test]
+    fn lenient_ignores_unknown_tiles() {
+        let mut report = LoadReport::new("Test".to_string());
+        let row = vec!["20"; WIDTH].join(",");
+        let content = format!(r#"{{"name": "Test", "map": [[{}]]}}"#, row);
+        assert!(info(LoadMode::Lenient)
+            .parse_map(&content, &mut report)
+            .is_ok());
+        assert!(report.is_empty());
+    }
+}
+
+mod config_report {
+    use super::super::report::DiagnosticKind;
+    use super::super::report::LoadReport;
+    use super::super::{LoadMode, MapInfo};
+
+    #[test]
+    fn missing_and_unlisted_maps() {
+        let toml: toml::Value = toml::from_str(
+            r#"
+            [Maps]
+            version = "0.1.0"
+            maps = ["Listed", "Missing"]
+
+            [Listed]
+            version = "0.1.0"
+            path = "./maps/listed.map"
+
+            [Unlisted]
+            version = "0.1.0"
+            path = "./maps/unlisted.map"
+            "#,
+        )
+        .unwrap();
+        let mut report = LoadReport::new("config.toml".to_string());
+        let maps = MapInfo::from_conf(&toml, LoadMode::Lenient, false, &mut report).unwrap();
+        assert_eq!(maps.len(), 1);
+        let kinds: Vec<_> = report.entries().iter().map(|d| d.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::MissingInConfig("Missing".to_string()),
+                DiagnosticKind::NotListed("Unlisted".to_string()),
+            ]
+        );
+    }
+}
+
+mod tile {
+    use super::super::tile::Tile;
+    use super::super::MAX_TILE;
+
+    #[test]
+    fn codes_roundtrip() {
+        for code in 0..=MAX_TILE {
+            assert_eq!(Tile::from_code(code).unwrap().code(), code);
+        }
+        assert_eq!(Tile::from_code(MAX_TILE + 1), None);
+    }
+
+    #[test]
+    fn glyphs_are_unique() {
+        for tile in Tile::ALL.iter() {
+            assert_eq!(Tile::from_glyph(tile.glyph()), Some(*tile));
+        }
+    }
+}
+
+mod format {
+    use super::super::format::{Cell, MapFormat, RawMap};
+
+    fn expected() -> RawMap {
+        RawMap {
+            name: Some("Test".to_string()),
+            features: Some(vec!["dark".to_string(), "rain".to_string()]),
+            rows: vec![
+                vec![Cell::Code(1), Cell::Code(1), Cell::Code(1)],
+                vec![Cell::Code(1), Cell::Code(5), Cell::Code(11)],
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn json() {
+        let content =
+            r#"{"name": "Test", "features": ["dark", "rain"], "map": [[1, 1, 1], [1, 5, 11]]}"#;
+        let raw = MapFormat::JSON.loader().unwrap().parse(content).unwrap();
+        assert_eq!(raw, expected());
+    }
+
+    #[test]
+    fn toml() {
+        let content = r#"
+            name = "Test"
+            features = ["dark", "rain"]
+            map = [[1, 1, 1], [1, 5, 11]]
+        "#;
+        let raw = MapFormat::TOML.loader().unwrap().parse(content).unwrap();
+        assert_eq!(raw, expected());
+    }
+
+    #[test]
+    fn text() {
+        let content = "; comment\nname: Test\nfeatures: dark, rain\n---\n###\n#ST\n\n";
+        let raw = MapFormat::Text.loader().unwrap().parse(content).unwrap();
+        assert_eq!(raw, expected());
+    }
+
+    #[test]
+    fn text_invalid_glyph() {
+        let content = "name: Test\n---\n#?#\n";
+        let raw = MapFormat::Text.loader().unwrap().parse(content).unwrap();
+        assert_eq!(raw.rows[0][1], Cell::Invalid);
+    }
+
+    #[test]
+    fn text_without_separator() {
+        assert!(MapFormat::Text
+            .loader()
+            .unwrap()
+            .parse("name: Test\n###\n")
+            .is_err());
+    }
+
+    #[test]
+    fn tiled() {
+        use super::super::format::tiled::TiledSettings;
+
+        let content = r#"{
+            "width": 3, "height": 2, "orientation": "orthogonal",
+            "properties": [
+                {"name": "name", "type": "string", "value": "Test"},
+                {"name": "features", "type": "string", "value": "dark, rain"}
+            ],
+            "layers": [
+                {"type": "objectgroup", "name": "objects", "objects": []},
+                {"type": "tilelayer", "name": "background", "width": 3, "height": 2,
+                 "data": [0, 0, 0, 0, 0, 0]},
+                {"type": "tilelayer", "name": "ground", "width": 3, "height": 2,
+                 "data": [3, 3, 3, 3, 2147483652, 9]}
+            ]
+        }"#;
+        let toml: toml::Value = toml::from_str(
+            r#"
+            layer = "ground"
+            [tiles]
+            3 = 1
+            4 = 5
+            9 = 11
+            "#,
+        )
+        .unwrap();
+        let settings = TiledSettings::from_conf(&toml, "Test").unwrap();
+        let raw = MapFormat::Tiled(settings.clone())
+            .loader()
+            .unwrap()
+            .parse(content)
+            .unwrap();
+        assert_eq!(raw, expected());
+
+        let mut settings = settings;
+        settings.layer = None;
+        settings.tiles.remove(&3);
+        let raw = MapFormat::Tiled(settings)
+            .loader()
+            .unwrap()
+            .parse(content)
+            .unwrap();
+        assert_eq!(raw.rows[0][0], Cell::Code(0));
+    }
+
+    #[test]
+    fn tiled_unmapped() {
+        use super::super::format::tiled::TiledSettings;
+
+        let content = r#"{"layers": [{"type": "tilelayer", "width": 2, "data": [1, 2]}]}"#;
+        let raw = MapFormat::Tiled(TiledSettings::default())
+            .loader()
+            .unwrap()
+            .parse(content)
+            .unwrap();
+        assert_eq!(raw.name, None);
+        assert_eq!(raw.rows, vec![vec![Cell::Unmapped(1), Cell::Unmapped(2)]]);
+    }
+}
+
+mod writer {
+    use super::super::format::MapFormat;
+    use super::super::report::{DiagnosticKind, LoadReport};
+    use super::super::{Map, MapInfo, WIDTH};
+    use semver::Version;
+
+    fn load(content: &str, format: MapFormat) -> (Map, LoadReport) {
+        let info = MapInfo::new(
+            "Test".to_string(),
+            "./maps/test.map".to_string(),
+            Version::new(0, 1, 0),
+            format,
+            None,
+            false,
+        );
+        let mut report = LoadReport::new("Test".to_string());
+        let map = info.parse_map(content, &mut report).unwrap();
+        (map, report)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let (map, _) = load(
+            r#"{"name": "Test", "features": ["dark"], "difficulty": "hard", "tags": ["a", "b"], "max_players": 4, "map": [[0, 1, 2, 3, 4, 5, 6], [7, 8, 9, 10, 11, 12]]}"#,
+            MapFormat::JSON,
+        );
+        for format in [MapFormat::JSON, MapFormat::TOML, MapFormat::Text] {
+            let mut lossy = LoadReport::new("Test".to_string());
+            let content = format.writer().unwrap().write(&map, &mut lossy).unwrap();
+            assert!(lossy.is_empty());
+            let (written, report) = load(&content, format);
+            assert!(report.is_empty(), "{}", report);
+            assert_eq!(written.rows(), map.rows());
+            assert_eq!(written.feature_list(), "dark");
+            assert_eq!(written.meta(), map.meta());
+        }
+    }
+
+    #[test]
+    fn roundtrip_empty() {
+        let (map, _) = load(r#"{"name": "Test", "map": []}"#, MapFormat::JSON);
+        assert!(map.rows().is_empty());
+        for format in [MapFormat::JSON, MapFormat::TOML, MapFormat::Text] {
+            let mut lossy = LoadReport::new("Test".to_string());
+            let content = format.writer().unwrap().write(&map, &mut lossy).unwrap();
+            let (written, _) = load(&content, format);
+            assert!(written.rows().is_empty(), "{}", content);
+        }
+    }
+
+    #[test]
+    fn text_is_lossy() {
+        let (map, _) = load(r#"{"name": "Test", "map": [[13]]}"#, MapFormat::JSON);
+        let mut lossy = LoadReport::new("Test".to_string());
+        let content = MapFormat::Text
+            .writer()
+            .unwrap()
+            .write(&map, &mut lossy)
+            .unwrap();
+        assert_eq!(lossy.entries().len(), 1);
+        assert_eq!(
+            (lossy.entries()[0].row, lossy.entries()[0].column),
+            (Some(0), Some(0))
+        );
+        match &lossy.entries()[0].kind {
+            DiagnosticKind::Lossy(_) => (),
+            kind => panic!("unexpected {:?}", kind),
+        }
+        assert!(content.ends_with(&format!("{}\n", "#".repeat(WIDTH))));
+    }
+
+    #[test]
+    fn tiled_is_not_writable() {
+        use super::super::format::tiled::TiledSettings;
+        assert!(MapFormat::Tiled(TiledSettings::default()).writer().is_err());
+    }
+}
+
+mod generator {
+    use super::super::generator::{generate, GeneratorSettings};
+    use super::super::report::LoadReport;
+    use super::super::tile::Tile;
+    use super::super::{MapFormat, MapInfo, MapPlaces, WIDTH};
+    use crate::error::ErrorKind;
+    use semver::Version;
+    use std::collections::HashMap;
+
+    fn count(rows: &[[u8; WIDTH]], tiles: &[Tile]) -> usize {
+        rows.iter()
+            .flat_map(|r| r.iter())
+            .filter(|c| tiles.iter().any(|t| t.code() == **c))
+            .count()
+    }
+
+    #[test]
+    fn same_seed_same_map() {
+        let settings = GeneratorSettings::default();
+        assert_eq!(
+            generate("a", &settings, 7).rows(),
+            generate("b", &settings, 7).rows()
+        );
+        assert_ne!(
+            generate("a", &settings, 7).rows(),
+            generate("a", &settings, 8).rows()
+        );
+    }
+
+    #[test]
+    fn start_reaches_exit() {
+        let settings = GeneratorSettings {
+            wall_density: 0.9,
+            ..Default::default()
+        };
+        for seed in 0..50 {
+            let map = generate("random", &settings, seed);
+            let rows = map.rows();
+            assert_eq!(rows.len(), settings.height);
+            assert_eq!(count(rows, &[Tile::Start]), 1);
+            assert!(map.analysis().solvable(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn places_items() {
+        let settings = GeneratorSettings {
+            wall_density: 0.0,
+            ..Default::default()
+        };
+        let map = generate("random", &settings, 3);
+        let berries = [Tile::BerryEnergy, Tile::BerryHp, Tile::BerryXp];
+        assert_eq!(count(map.rows(), &berries), settings.berries);
+        assert_eq!(
+            count(map.rows(), &[Tile::Enemy, Tile::FastEnemy]),
+            settings.enemies
+        );
+        assert_eq!(count(map.rows(), &[Tile::Teleport]), settings.teleports);
+    }
+
+    #[test]
+    fn settings_from_conf() {
+        let toml: toml::Value = toml::from_str("height = 5\nwall_density = 0.5").unwrap();
+        let settings = GeneratorSettings::from_conf(&toml, "random").unwrap();
+        assert_eq!(settings.height, 5);
+        assert_eq!(settings.enemies, GeneratorSettings::default().enemies);
+
+        let toml: toml::Value = toml::from_str("wall_density = 1.5").unwrap();
+        assert!(GeneratorSettings::from_conf(&toml, "random").is_err());
+        let toml: toml::Value = toml::from_str("wall_density = 0").unwrap();
+        let settings = GeneratorSettings::from_conf(&toml, "random").unwrap();
+        assert_eq!(settings.wall_density, 0.0);
+        for density in ["wall_density = 1", "wall_density = \"0.5\""] {
+            let toml: toml::Value = toml::from_str(density).unwrap();
+            match GeneratorSettings::from_conf(&toml, "random") {
+                Err(err) => assert_eq!(
+                    err.kind(),
+                    ErrorKind::NotParsable(
+                        "random.wall_density: must be a number between 0.0 and 0.9".to_string()
+                    )
+                ),
+                Ok(_) => panic!("accepted {}", density),
+            }
+        }
+        let toml: toml::Value = toml::from_str("height = 2").unwrap();
+        assert!(GeneratorSettings::from_conf(&toml, "random").is_err());
+    }
+
+    #[test]
+    fn get_with_seed() {
+        let mut maps = HashMap::new();
+        maps.insert(
+            "random".to_string(),
+            MapInfo::new(
+                "random".to_string(),
+                String::new(),
+                Version::new(0, 1, 0),
+                MapFormat::Random(GeneratorSettings::default()),
+                None,
+                false,
+            ),
+        );
+        let places = MapPlaces {
+            p_version: Version::new(0, 1, 0),
+            p_maps: maps,
+            p_report: LoadReport::default(),
+            p_directory: None,
+        };
+        let a = places.get("random 42").unwrap();
+        assert_eq!(a.name(), "random 42");
+        assert_eq!(a.rows(), places.get("random 42").unwrap().rows());
+        assert_eq!(
+            places.get("random").unwrap().rows(),
+            places.get("random 1996").unwrap().rows()
+        );
+        assert!(places.get("other 42").is_err());
+    }
+}
+
+mod analysis {
+    use super::super::analysis::Analysis;
+    use super::super::report::{DiagnosticKind, LoadReport};
+    use super::super::WIDTH;
+
+    /// builds rows from lines of digits, padded with solid blocks
+    fn rows(lines: &[&str]) -> Vec<[u8; WIDTH]> {
+        lines
+            .iter()
+            .map(|line| {
+                let mut row = [1; WIDTH];
+                for (x, c) in line.chars().enumerate() {
+                    row[x] = match c {
+                        'T' => 11,
+                        c => c.to_digit(10).unwrap() as u8,
+                    };
+                }
+                row
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shortest_path() {
+        let analysis = Analysis::new(&rows(&["1111", "1500", "1111"]));
+        assert!(!analysis.solvable());
+        assert_eq!(analysis.exits(), 0);
+        assert_eq!(analysis.starts(), &[(1, 1)]);
+
+        let mut map = rows(&["1111", "1500", "1101", "1111"]);
+        for cell in map[1][3..].iter_mut() {
+            *cell = 0;
+        }
+        let analysis = Analysis::new(&map);
+        assert!(analysis.solvable());
+        assert_eq!(analysis.exits(), 1);
+        assert_eq!(analysis.shortest_path(), Some(WIDTH - 2));
+    }
+
+    #[test]
+    fn start_on_border() {
+        // a start point is no exit, even on the border
+        let analysis = Analysis::new(&rows(&["1511", "1111"]));
+        assert!(!analysis.solvable());
+        assert_eq!(analysis.exits(), 0);
+
+        let analysis = Analysis::new(&rows(&["1501", "1111"]));
+        assert!(analysis.solvable());
+        assert_eq!(analysis.shortest_path(), Some(1));
+    }
+
+    #[test]
+    fn teleports() {
+        // start and exit are only connected through the teleports
+        let mut map = rows(&["1111", "15T1", "1111", "1T0"]);
+        map[3][2] = 0;
+        let analysis = Analysis::new(&map);
+        assert!(analysis.solvable());
+        // start -> teleport -> linked teleport on the border
+        assert_eq!(analysis.shortest_path(), Some(2));
+    }
+
+    #[test]
+    fn unsolvable() {
+        let analysis = Analysis::new(&rows(&["1101", "1511", "1171", "1101"]));
+        assert!(!analysis.solvable());
+        assert_eq!(analysis.exits(), 2);
+        assert_eq!(analysis.stuck_starts(), &[(1, 1)]);
+        assert_eq!(analysis.unreachable_berries(), &[(2, 2)]);
+        assert_eq!(analysis.shortest_path(), None);
+
+        let analysis = Analysis::new(&rows(&["1111", "1001", "1111"]));
+        assert!(!analysis.solvable());
+        assert!(analysis.starts().is_empty());
+    }
+
+    #[test]
+    fn load_report() {
+        let mut report = LoadReport::default();
+        Analysis::new(&rows(&["1101", "1511", "1171", "1101"])).report(&mut report);
+        let kinds: Vec<(DiagnosticKind, Option<usize>)> =
+            report.warnings().map(|d| (d.kind.clone(), d.row)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (DiagnosticKind::StuckStart, Some(1)),
+                (DiagnosticKind::UnreachableBerry, Some(2))
+            ]
+        );
+
+        let mut report = LoadReport::default();
+        Analysis::new(&rows(&["1111", "1001", "1111"])).report(&mut report);
+        let kinds: Vec<DiagnosticKind> = report.warnings().map(|d| d.kind.clone()).collect();
+        assert_eq!(kinds, vec![DiagnosticKind::NoStart, DiagnosticKind::NoExit]);
+        assert!(!report.has_errors());
+    }
+}
+
+mod meta {
+    use super::super::format::MapFormat;
+    use super::super::meta::{Difficulty, MapMeta};
+    use super::super::report::LoadReport;
+    use super::super::{LoadMode, MapInfo};
+
+    #[test]
+    fn from_value() {
+        let toml: toml::Value = toml::from_str(
+            r#"
+            description = "A small map"
+            difficulty = "Easy"
+            min_players = 1
+            max_players = 4
+            tags = ["tutorial", "small"]
+            play_time = 5
+            thumbnail = "./maps/small.png"
+            "#,
+        )
+        .unwrap();
+        let meta = MapMeta::from_value(&toml, "Small").unwrap();
+        assert_eq!(meta.description.as_deref(), Some("A small map"));
+        assert_eq!(meta.difficulty, Some(Difficulty::Easy));
+        assert_eq!((meta.min_players, meta.max_players), (Some(1), Some(4)));
+        assert_eq!(meta.tags, vec!["tutorial", "small"]);
+        assert_eq!(meta.play_time, Some(5));
+        assert_eq!(meta.thumbnail.as_deref(), Some("./maps/small.png"));
+
+        let empty = MapMeta::from_value(&toml::Value::Table(Default::default()), "Small");
+        assert!(empty.unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid() {
+        for content in [
+            "difficulty = \"impossible\"",
+            "min_players = 0",
+            "min_players = 4\nmax_players = 2",
+            "play_time = \"long\"",
+            "tags = [1]",
+        ] {
+            let toml: toml::Value = toml::from_str(content).unwrap();
+            assert!(MapMeta::from_value(&toml, "Small").is_err(), "{}", content);
+        }
+    }
+
+    #[test]
+    fn config_wins_over_file() {
+        let toml: toml::Value = toml::from_str(
+            r#"
+            version = "0.1.0"
+            path = "./maps/test.map"
+            play_time = 10
+            "#,
+        )
+        .unwrap();
+        let info =
+            MapInfo::from_conf_one(&toml, "Test".to_string(), LoadMode::Lenient, false).unwrap();
+        assert_eq!(info.meta().play_time, Some(10));
+
+        let mut report = LoadReport::default();
+        let map = info
+            .parse_map(
+                r#"{"name": "Test", "play_time": 5, "max_players": 2, "map": [[1]]}"#,
+                &mut report,
+            )
+            .unwrap();
+        assert_eq!(map.meta().play_time, Some(10));
+        assert_eq!(map.meta().max_players, Some(2));
+        assert!(map.to_string().contains("\"p_meta\""));
+    }
+
+    #[test]
+    fn text_header() {
+        let content = "name: Test\ndifficulty: expert\ntags: a, b\nplay_time: 3\n---\n#S#\n";
+        let raw = MapFormat::Text.loader().unwrap().parse(content).unwrap();
+        assert_eq!(raw.meta.difficulty, Some(Difficulty::Expert));
+        assert_eq!(raw.meta.tags, vec!["a", "b"]);
+        assert_eq!(raw.meta.play_time, Some(3));
+    }
+}
+
+mod render {
+    use super::super::generator::{generate, GeneratorSettings};
+    use super::super::render::{png, svg};
+    use super::super::WIDTH;
+
+    #[test]
+    fn svg_has_one_rect_per_tile() {
+        let map = generate("a < b", &GeneratorSettings::default(), 1);
+        let image = svg(&map, 4);
+        assert!(image.contains("width=\"112\""));
+        assert!(image.contains("<title>a &lt; b</title>"));
+        assert_eq!(image.matches("<rect").count(), WIDTH * map.rows().len());
+    }
+
+    #[test]
+    fn png_is_valid() {
+        let settings = GeneratorSettings {
+            height: 3,
+            ..Default::default()
+        };
+        let map = generate("random", &settings, 1);
+        let image = png(&map, 2);
+        assert_eq!(&image[..8], b"\x89PNG\r\n\x1a\n");
+        // IHDR: width and height in pixels
+        assert_eq!(&image[12..16], b"IHDR");
+        assert_eq!(&image[16..20], &(WIDTH as u32 * 2).to_be_bytes());
+        assert_eq!(&image[20..24], &6u32.to_be_bytes());
+        // IEND chunk with its well known checksum
+        assert_eq!(
+            &image[image.len() - 12..],
+            b"\x00\x00\x00\x00IEND\xae\x42\x60\x82"
+        );
+    }
+}
+
+mod directory {
+    use super::super::report::DiagnosticKind;
+    use super::super::MapPlaces;
+    use crate::testing::TempDir;
+    use semver::Version;
+
+    /// writes a file into the directory of the test, returns its path
+    fn write(dir: &TempDir, file: &str, content: &str) -> String {
+        dir.write(file, content)
+            .unwrap()
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn discovers_maps() {
+        let dir = TempDir::new("discover").unwrap();
+        let maps = dir.join("maps").to_string_lossy().to_string();
+        write(
+            &dir,
+            "maps/a.map",
+            r#"{"name": "A", "version": "0.2.0", "author": "Ann", "map": [[1]]}"#,
+        );
+        write(&dir, "maps/b.txt", "name: B\nauthor: Bo, Cy\n---\n#S#\n");
+        write(&dir, "maps/notes.md", "not a map");
+        let config = write(
+            &dir,
+            "config.toml",
+            &format!("[Maps]\nversion = \"0.1.0\"\ndirectory = {:?}\n", maps),
+        );
+
+        let places = MapPlaces::new(&config, false).unwrap();
+        let mut names = places.available_maps();
+        names.sort();
+        assert_eq!(names, vec!["A", "B"]);
+        assert!(places.report().is_empty(), "{}", places.report());
+        assert_eq!(places.p_maps["A"].version(), &Version::new(0, 2, 0));
+        assert_eq!(places.get_author("B"), Some("BoCy".to_string()));
+        assert!(places.get("B").is_ok());
+    }
+
+    #[test]
+    fn config_wins() {
+        let dir = TempDir::new("conflict").unwrap();
+        let maps = dir.join("maps").to_string_lossy().to_string();
+        let a = write(&dir, "maps/a.map", r#"{"name": "A", "map": [[1]]}"#);
+        let b = write(&dir, "maps/b.map", r#"{"name": "A", "map": [[2]]}"#);
+        write(&dir, "maps/c.map", r#"{"name": "C", "map": [[1]]}"#);
+        write(&dir, "maps/d.map", r#"{"name": "C", "map": [[2]]}"#);
+        write(&dir, "maps/broken.map", "{");
+        let config = write(
+            &dir,
+            "config.toml",
+            &format!(
+                "[Maps]\nversion = \"0.1.0\"\nmaps = [\"A\", \"Same\"]\ndirectory = {:?}\n\n[A]\nversion = \"0.1.0\"\npath = {:?}\n\n[Same]\nversion = \"0.1.0\"\npath = {:?}\n",
+                maps, b, a
+            ),
+        );
+
+        let places = MapPlaces::new(&config, false).unwrap();
+        let mut names = places.available_maps();
+        names.sort();
+        assert_eq!(names, vec!["A", "C", "Same"]);
+        // config wins, a.map is used by "Same" and not reported
+        assert_eq!(places.get("A").unwrap().rows()[0][0], 2);
+        // first file wins
+        assert_eq!(places.get("C").unwrap().rows()[0][0], 1);
+
+        let kinds: Vec<&DiagnosticKind> =
+            places.report().entries().iter().map(|d| &d.kind).collect();
+        assert!(
+            matches!(kinds[0], DiagnosticKind::InvalidConfig(reason) if reason.contains("broken.map"))
+        );
+        assert!(
+            matches!(kinds[1], DiagnosticKind::Conflict { name, path } if name == "C" && path.ends_with("d.map"))
+        );
+        assert_eq!(kinds.len(), 2);
+    }
+
+    #[test]
+    fn missing_directory() {
+        let dir = TempDir::new("missing").unwrap();
+        let config = write(
+            &dir,
+            "config.toml",
+            "[Maps]\nversion = \"0.1.0\"\ndirectory = \"/does/not/exist\"\n",
+        );
+        let places = MapPlaces::new(&config, false).unwrap();
+        assert!(places.available_maps().is_empty());
+        assert!(places.report().has_errors());
+    }
+}
+
+mod store {
+    use super::super::store::MapStore;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn reload_swaps_maps() {
+        let dir = TempDir::new("store").unwrap();
+        let config = dir.join("config.toml").to_string_lossy().to_string();
+        let random = "version = \"0.1.0\"\nformat = \"random\"\n";
+        fs::write(
+            &config,
+            format!(
+                "[Maps]\nversion = \"0.1.0\"\nmaps = [\"a\"]\n[a]\n{}",
+                random
+            ),
+        )
+        .unwrap();
+
+        let store = MapStore::new(&config, false).unwrap();
+        let old = store.current();
+        assert_eq!(old.available_maps(), vec!["a"]);
+
+        // a broken config keeps the old maps
+        fs::write(&config, "[Maps]\nmaps = [").unwrap();
+        assert!(store.reload().is_err());
+        assert_eq!(store.current().available_maps(), vec!["a"]);
+
+        fs::write(
+            &config,
+            format!(
+                "[Maps]\nversion = \"0.1.0\"\nmaps = [\"b\"]\n[b]\n{}",
+                random
+            ),
+        )
+        .unwrap();
+        store.reload().unwrap();
+        assert_eq!(store.current().available_maps(), vec!["b"]);
+        // snapshots taken before the reload still work
+        assert!(old.get("a 42").is_ok());
+    }
+}
+
+mod schema {
+    use super::super::report::{DiagnosticKind, LoadReport};
+    use super::super::schema::{migrate, ConfigSchema};
+    use super::super::LoadMode;
+    use crate::error::ErrorKind;
+    use semver::Version;
+
+    const V1: &str = r#"
+[Maps]
+version = "0.1.0"
+mode = "strict"
+maps = ["a", "Missing"]
+
+[a]
+version = "0.1.0"
+format = "random"
+
+[b]
+version = "0.1.0"
+path = "./maps/b.map"
+
+[Server]
+port = 1996
+"#;
+
+    fn load(content: &toml::Value) -> Vec<String> {
+        let version = content["Maps"]["version"].as_str().unwrap();
+        let schema = ConfigSchema::for_version(&Version::parse(version).unwrap()).unwrap();
+        let mut report = LoadReport::new("config.toml".to_string());
+        let maps = schema
+            .load(content, LoadMode::default(), false, &mut report)
+            .unwrap();
+        let mut names: Vec<String> = maps.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn for_version() {
+        let schema = |v| ConfigSchema::for_version(&Version::parse(v).unwrap());
+        assert_eq!(schema("0.1.0").unwrap(), ConfigSchema::V1);
+        assert_eq!(schema("0.1.9").unwrap(), ConfigSchema::V1);
+        assert_eq!(schema("0.2.1").unwrap(), ConfigSchema::V2);
+
+        let err = schema("0.3.0").unwrap_err();
+        match err.kind() {
+            ErrorKind::VersionNotSupported(reason) => {
+                assert!(reason.contains("0.3.0"), "{}", reason);
+                assert!(reason.contains(&ConfigSchema::V2.to_string()), "{}", reason);
+            }
+            kind => panic!("wrong error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn load_v2() {
+        let content: toml::Value = toml::from_str(
+            "[Maps]\nversion = \"0.2.0\"\n\n[map.a]\nversion = \"0.1.0\"\nformat = \"random\"\n\n[map.b]\nversion = \"0.1.0\"\nformat = \"random\"\nenabled = false\n",
+        )
+        .unwrap();
+        assert_eq!(load(&content), vec!["a"]);
+    }
+
+    #[test]
+    fn migrate_v1() {
+        let content: toml::Value = toml::from_str(V1).unwrap();
+        let mut report = LoadReport::new("config.toml".to_string());
+        let migrated = migrate(&content, &mut report).unwrap();
+
+        let kinds: Vec<&DiagnosticKind> = report.entries().iter().map(|d| &d.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &DiagnosticKind::MissingInConfig("Missing".to_string()),
+                &DiagnosticKind::NotListed("b".to_string()),
+            ]
+        );
+
+        // written and read again as the newest schema
+        let migrated: toml::Value = toml::from_str(&toml::to_string(&migrated).unwrap()).unwrap();
+        assert_eq!(migrated["Maps"]["version"].as_str(), Some("0.2.0"));
+        assert_eq!(migrated["Maps"]["mode"].as_str(), Some("strict"));
+        assert!(migrated["Maps"].get("maps").is_none());
+        assert_eq!(migrated["map"]["b"]["enabled"].as_bool(), Some(false));
+        assert_eq!(migrated["Server"]["port"].as_integer(), Some(1996));
+        assert!(migrated.get("a").is_none());
+        assert_eq!(load(&migrated), load(&content));
+
+        // the newest schema is kept as it is
+        let mut report = LoadReport::new("config.toml".to_string());
+        assert_eq!(migrate(&migrated, &mut report).unwrap(), migrated);
+        assert!(report.is_empty());
+    }
+}
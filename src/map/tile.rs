@@ -0,0 +1,176 @@
+use colored::Color;
+use std::fmt;
+
+/// Tile is a single block of a `Map`
+///
+/// The numbers are the codes used in the map files and sent to the client,
+/// see the table on `Map`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Tile {
+    /// 0: nothing, the player can walk here
+    None,
+
+    /// 1: solid block
+    Solid,
+
+    /// 2: water
+    Water,
+
+    /// 3: trap
+    Trap,
+
+    /// 4: moving platform
+    MovingPlatform,
+
+    /// 5: start point of a player
+    Start,
+
+    /// 6: berry giving energy
+    BerryEnergy,
+
+    /// 7: berry giving HP
+    BerryHp,
+
+    /// 8: berry giving XP
+    BerryXp,
+
+    /// 9: enemy with 100% speed
+    Enemy,
+
+    /// 10: enemy with 150% speed and 120% damage
+    FastEnemy,
+
+    /// 11: teleport
+    Teleport,
+
+    /// 12: moving platform
+    MovingPlatform2,
+}
+
+impl Tile {
+    /// every tile ordered by code
+    pub const ALL: [Tile; 13] = [
+        Tile::None,
+        Tile::Solid,
+        Tile::Water,
+        Tile::Trap,
+        Tile::MovingPlatform,
+        Tile::Start,
+        Tile::BerryEnergy,
+        Tile::BerryHp,
+        Tile::BerryXp,
+        Tile::Enemy,
+        Tile::FastEnemy,
+        Tile::Teleport,
+        Tile::MovingPlatform2,
+    ];
+
+    /// returns the tile for the given code, or None if the code is unknown
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::map::tile::Tile;
+    /// assert_eq!(Tile::from_code(5), Some(Tile::Start));
+    /// assert_eq!(Tile::from_code(13), None);
+    /// ```
+    pub fn from_code(code: u8) -> Option<Tile> {
+        Self::ALL.get(code as usize).cloned()
+    }
+
+    /// returns the tile drawn with the given glyph
+    pub fn from_glyph(glyph: char) -> Option<Tile> {
+        Self::ALL.iter().find(|t| t.glyph() == glyph).cloned()
+    }
+
+    /// returns the code used in map files
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// returns true for the three kinds of berries
+    pub fn is_berry(self) -> bool {
+        matches!(self, Tile::BerryEnergy | Tile::BerryHp | Tile::BerryXp)
+    }
+
+    /// returns the block and variant as written in the table on `Map`
+    pub fn description(self) -> &'static str {
+        match self {
+            Tile::None => "None",
+            Tile::Solid => "Solid Block",
+            Tile::Water => "Water",
+            Tile::Trap => "Trap",
+            Tile::MovingPlatform => "Moving platform",
+            Tile::Start => "Start point (player)",
+            Tile::BerryEnergy => "Berry (Energy)",
+            Tile::BerryHp => "Berry (HP)",
+            Tile::BerryXp => "Berry (XP)",
+            Tile::Enemy => "Enemy (100% speed)",
+            Tile::FastEnemy => "Enemy (150% speed; 120% damage)",
+            Tile::Teleport => "Teleport",
+            Tile::MovingPlatform2 => "Moving platform",
+        }
+    }
+
+    /// returns the ascii character used to draw the tile
+    pub fn glyph(self) -> char {
+        match self {
+            Tile::None => '.',
+            Tile::Solid => '#',
+            Tile::Water => '~',
+            Tile::Trap => '^',
+            Tile::MovingPlatform => '=',
+            Tile::Start => 'S',
+            Tile::BerryEnergy => 'e',
+            Tile::BerryHp => 'h',
+            Tile::BerryXp => 'x',
+            Tile::Enemy => 'E',
+            Tile::FastEnemy => 'F',
+            Tile::Teleport => 'T',
+            Tile::MovingPlatform2 => '-',
+        }
+    }
+
+    /// returns the terminal color used to draw the tile
+    pub fn color(self) -> Color {
+        match self {
+            Tile::None => Color::BrightBlack,
+            Tile::Solid => Color::White,
+            Tile::Water => Color::Blue,
+            Tile::Trap => Color::Magenta,
+            Tile::MovingPlatform | Tile::MovingPlatform2 => Color::Yellow,
+            Tile::Start => Color::BrightGreen,
+            Tile::BerryEnergy => Color::BrightYellow,
+            Tile::BerryHp => Color::BrightRed,
+            Tile::BerryXp => Color::BrightMagenta,
+            Tile::Enemy => Color::Red,
+            Tile::FastEnemy => Color::BrightRed,
+            Tile::Teleport => Color::Cyan,
+        }
+    }
+
+    /// returns the color used to draw the tile in images, as `(red, green, blue)`
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            Tile::None => (0xf0, 0xf0, 0xe0),
+            Tile::Solid => (0x4a, 0x4a, 0x4a),
+            Tile::Water => (0x3a, 0x7b, 0xd5),
+            Tile::Trap => (0x9b, 0x4d, 0xca),
+            Tile::MovingPlatform => (0xc8, 0x96, 0x1e),
+            Tile::Start => (0x2e, 0xcc, 0x40),
+            Tile::BerryEnergy => (0xff, 0xdc, 0x00),
+            Tile::BerryHp => (0xff, 0x41, 0x36),
+            Tile::BerryXp => (0xf0, 0x12, 0xbe),
+            Tile::Enemy => (0xb1, 0x0d, 0x0d),
+            Tile::FastEnemy => (0xff, 0x85, 0x1b),
+            Tile::Teleport => (0x39, 0xcc, 0xcc),
+            Tile::MovingPlatform2 => (0xa0, 0x6e, 0x14),
+        }
+    }
+}
+
+impl fmt::Display for Tile {
+    /// standart formater for print! macro
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
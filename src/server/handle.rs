@@ -0,0 +1,413 @@
+use super::super::error::{Error, ErrorKind};
+use super::super::map::store::MapStore;
+use super::super::threads::ThreadPool;
+use super::super::Config;
+use super::listener::{ListenAddr, Listener};
+use super::request::{self, Query, Reply};
+use super::shutdown::{Event, Sessions};
+use super::transport::{Connection, Writer};
+use super::{http, Job, Message};
+use colored::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// time an accept thread waits after a failed accept, doubled while it keeps failing
+pub const ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+
+/// longest time an accept thread waits after a failed accept
+pub const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// ServerState is a snapshot of a running server
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerState {
+    /// false after the server started to shut down
+    pub running: bool,
+
+    /// number of clients being served
+    pub clients: usize,
+
+    /// number of maps available
+    pub maps: usize,
+}
+
+/// ServerHandle controls a server started with `Config::start`
+///
+/// Dropping the handle shuts the server down.
+///
+/// # Example
+/// ```
+/// use poke_escape_server::Config;
+/// use poke_escape_server::server::listener::ListenAddr;
+/// let mut config = Config::new();
+/// config.listen = vec![ListenAddr::Tcp("127.0.0.1:0".to_string())];
+/// let server = config.start().unwrap();
+/// assert!(server.state().running);
+/// server.shutdown().unwrap();
+/// ```
+pub struct ServerHandle {
+    /// addresses the server is bound to
+    p_addrs: Vec<ListenAddr>,
+
+    /// events of the accept loop, used to stop it
+    p_events: mpsc::Sender<Event>,
+
+    /// set when the server stops, read by the accept threads
+    p_stopped: Arc<AtomicBool>,
+
+    /// clients being served
+    p_sessions: Arc<Sessions>,
+
+    /// maps of the server
+    p_maps: Arc<MapStore>,
+
+    /// channel to the coordinator of the clients
+    p_coordinator: mpsc::Sender<Message>,
+
+    /// thread running the accept loop
+    p_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// loads the maps, binds every listen address and starts serving
+    ///
+    /// # Errors
+    /// Returns the error of loading the maps, creating the ThreadPool or
+    /// binding an address. Nothing is left running in this case.
+    pub fn start(config: &Config) -> Result<Self> {
+        let maps = Arc::new(MapStore::new(&config.config, config.verbose)?);
+        Self::start_with_maps(config, maps)
+    }
+
+    /// starts serving already loaded maps
+    ///
+    /// Used by `Config::run`, which reloads the maps on SIGHUP.
+    pub fn start_with_maps(config: &Config, maps: Arc<MapStore>) -> Result<Self> {
+        Self::start_on(config, maps, &config.listen_addrs())
+    }
+
+    /// starts serving the maps on the given addresses instead of the configured
+    ///
+    /// Without addresses the server only serves connections handed to `serve`.
+    pub fn start_on(config: &Config, maps: Arc<MapStore>, addrs: &[ListenAddr]) -> Result<Self> {
+        let mut thread_pool = ThreadPool::new(config.threads)?;
+        if config.verbose {
+            thread_pool.verbose();
+        }
+
+        // bind every address before accepting, so an error stops nothing
+        let mut listeners = Vec::new();
+        for addr in addrs {
+            listeners.push(Listener::bind(addr)?);
+        }
+        let mut addrs = Vec::new();
+        for listener in &listeners {
+            addrs.push(listener.local_addr()?);
+        }
+
+        // accept on every listener concurrently
+        let (events_tx, events) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        for listener in listeners {
+            let events_tx = mpsc::Sender::clone(&events_tx);
+            let stopped = Arc::clone(&stopped);
+            thread::spawn(move || {
+                let mut backoff = ACCEPT_BACKOFF;
+                loop {
+                    let stream = listener.accept().map(|s| Box::new(s) as Connection);
+                    if stopped.load(Ordering::SeqCst) {
+                        break; // drops the listener
+                    }
+                    let failed = stream.is_err();
+                    if events_tx.send(Event::Client(stream)).is_err() {
+                        break;
+                    }
+                    // errors like EMFILE repeat at once, do not spin on them
+                    if failed {
+                        thread::sleep(backoff);
+                        backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                    } else {
+                        backoff = ACCEPT_BACKOFF;
+                    }
+                }
+            });
+        }
+
+        // coordinator of the clients, runs until every sender is dropped
+        let (coordinator, rx) = mpsc::channel();
+        super::server_client(rx, config.verbosity_level, Arc::clone(&maps)); // FIXME: verbosity level
+
+        let sessions = Arc::new(Sessions::new());
+        let serve = Serve {
+            config: config.clone(),
+            maps: Arc::clone(&maps),
+            sessions: Arc::clone(&sessions),
+            addrs: addrs.clone(),
+            stopped: Arc::clone(&stopped),
+            coordinator: mpsc::Sender::clone(&coordinator),
+        };
+        let thread = thread::spawn(move || serve.run(events, thread_pool));
+
+        Ok(ServerHandle {
+            p_addrs: addrs,
+            p_events: events_tx,
+            p_stopped: stopped,
+            p_sessions: sessions,
+            p_maps: maps,
+            p_coordinator: coordinator,
+            p_thread: Some(thread),
+        })
+    }
+
+    /// returns the addresses the server is bound to
+    ///
+    /// Tcp addresses have the real port, also when port 0 was configured.
+    pub fn local_addrs(&self) -> &[ListenAddr] {
+        &self.p_addrs
+    }
+
+    /// returns the current state of the server
+    pub fn state(&self) -> ServerState {
+        ServerState {
+            running: !self.p_stopped.load(Ordering::SeqCst),
+            clients: self.p_sessions.len(),
+            maps: self.p_maps.current().available_maps().len(),
+        }
+    }
+
+    /// returns the maps of the server, to reload them
+    pub fn maps(&self) -> Arc<MapStore> {
+        Arc::clone(&self.p_maps)
+    }
+
+    /// asks the coordinator about the clients, see `request::Query`
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::server::listener::ListenAddr;
+    /// use poke_escape_server::server::request::{Query, Reply};
+    /// use poke_escape_server::Config;
+    /// use std::time::Duration;
+    /// let mut config = Config::new();
+    /// config.listen = vec![ListenAddr::Tcp("127.0.0.1:0".to_string())];
+    /// let server = config.start().unwrap();
+    /// let reply = server.query(Query::Clients, Duration::from_secs(1)).unwrap();
+    /// assert_eq!(reply, Reply::Clients(Vec::new()));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `IoTimedOut` if the coordinator did not answer in time.
+    pub fn query(&self, query: Query, timeout: Duration) -> Result<Reply> {
+        request::ask(&self.p_coordinator, "admin", query, timeout)
+    }
+
+    /// serves a connection which was not accepted by a listener
+    ///
+    /// The connection is handled like a client of a listener, for example an
+    /// in-memory `transport::pipe` in tests.
+    ///
+    /// # Errors
+    /// Returns `IoNotConnected` if the server already stopped.
+    pub fn serve(&self, connection: Connection) -> Result<()> {
+        self.p_events
+            .send(Event::Client(Ok(connection)))
+            .map_err(|_| Error::from(std::io::ErrorKind::NotConnected))
+    }
+
+    /// returns a sender to stop the server from another thread
+    ///
+    /// Used to stop the server on signals, see `shutdown::stop_on_signals`.
+    pub fn events(&self) -> mpsc::Sender<Event> {
+        mpsc::Sender::clone(&self.p_events)
+    }
+
+    /// stops the server and waits until it stopped
+    ///
+    /// Clients get `shutdown_timeout` to leave, see `Config::run`.
+    pub fn shutdown(mut self) -> Result<()> {
+        self.stop("shutdown");
+        self.join()
+    }
+
+    /// waits until the server stopped, for example by a signal
+    pub fn wait(mut self) -> Result<()> {
+        self.join()
+    }
+
+    /// asks the accept loop to stop, does nothing if it already stopped
+    fn stop(&self, reason: &str) {
+        let _ = self.p_events.send(Event::Stop(reason.to_string()));
+    }
+
+    /// joins the thread of the accept loop
+    fn join(&mut self) -> Result<()> {
+        if let Some(thread) = self.p_thread.take() {
+            if thread.join().is_err() {
+                return Err(Error::new(ErrorKind::Unknown(
+                    "server thread panicked".to_string(),
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ServerHandle {
+    /// shuts the server down if it is still running
+    fn drop(&mut self) {
+        if self.p_thread.is_some() {
+            self.stop("handle dropped");
+            let _ = self.join();
+        }
+    }
+}
+
+/// Serve holds everything the accept loop needs
+struct Serve {
+    /// settings of the server
+    config: Config,
+
+    /// maps of the server
+    maps: Arc<MapStore>,
+
+    /// clients being served
+    sessions: Arc<Sessions>,
+
+    /// addresses the server is bound to
+    addrs: Vec<ListenAddr>,
+
+    /// set when the server stops
+    stopped: Arc<AtomicBool>,
+
+    /// channel to the coordinator of the clients
+    coordinator: mpsc::Sender<Message>,
+}
+
+impl Serve {
+    /// hands every client to the ThreadPool until `Event::Stop`
+    fn run(self, events: mpsc::Receiver<Event>, thread_pool: ThreadPool) {
+        let config = &self.config;
+        let thumbnails = Arc::new(http::Thumbnails::new());
+        let tx = &self.coordinator;
+
+        // handle incomming streams
+        for event in events {
+            let stream = match event {
+                Event::Client(Ok(stream)) => stream,
+                Event::Client(Err(err)) => {
+                    eprintln!("error creating stream: {}", err);
+                    continue;
+                }
+                Event::Stop(reason) => {
+                    if config.verbose {
+                        println!("got {}, {}", reason.yellow(), "stopping".red());
+                    }
+                    break;
+                }
+            };
+            if config.max_connections > 0 && self.sessions.len() >= config.max_connections {
+                if config.verbose {
+                    println!("{}: too many clients, closing connection", "limit".red());
+                }
+                continue; // dropping the stream closes it
+            }
+            if let Err(err) = stream
+                .set_read_timeout(config.read_timeout)
+                .and_then(|_| stream.set_write_timeout(config.write_timeout))
+            {
+                eprintln!("could not set timeouts: {}", err);
+                continue;
+            }
+            let registered = stream.try_clone().map(Writer::new).and_then(|writer| {
+                Ok((self.sessions.add(stream.as_ref(), writer.clone())?, writer))
+            });
+            let (session, writer) = match registered {
+                Ok(registered) => registered,
+                Err(err) => {
+                    eprintln!("could not register client: {}", err);
+                    continue;
+                }
+            };
+            let conf = Job {
+                stream,
+                writer,
+                verbose: config.verbose,
+                sender: mpsc::Sender::clone(tx),
+                maps: Arc::clone(&self.maps),
+                thumbnails: Arc::clone(&thumbnails),
+            };
+
+            // execute Job in ThreadPool
+            let sessions = Arc::clone(&self.sessions);
+            let verbose = config.verbose;
+            let executed = thread_pool.execute(move || {
+                // errors of a session are caused by its client
+                if let Err(err) = super::negotiate(conf) {
+                    if verbose {
+                        eprintln!("error while executing client handler: {}", err);
+                    }
+                }
+                sessions.remove(session);
+            });
+            if let Err(err) = executed {
+                eprintln!("could not hand client to a worker: {}", err);
+                self.sessions.remove(session);
+            }
+        }
+
+        // stop accepting, wake every accept thread so it drops its listener
+        self.stopped.store(true, Ordering::SeqCst);
+        for addr in &self.addrs {
+            wake(addr);
+        }
+
+        // let the clients leave before the workers are joined
+        if config.verbose {
+            println!(
+                "waiting up to {}s for {} clients",
+                config.shutdown_timeout.as_secs().to_string().yellow(),
+                self.sessions.len().to_string().yellow()
+            );
+        }
+        let notice = format!("shutdown {}", config.shutdown_timeout.as_secs());
+        let notified = self.sessions.notify(&notice);
+        if config.verbose {
+            println!("notified {} clients of the shutdown", notified);
+        }
+        if !self.sessions.wait_empty(config.shutdown_timeout) {
+            let closed = self.sessions.close_all();
+            if config.verbose {
+                println!(
+                    "closed {} clients after the deadline",
+                    closed.to_string().red()
+                );
+            }
+        }
+        drop(thread_pool); // joins the workers
+
+        for addr in &self.addrs {
+            if let ListenAddr::Unix(path) = addr {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// connects to the address once, so a blocked accept returns
+fn wake(addr: &ListenAddr) {
+    match addr {
+        ListenAddr::Tcp(addr) => {
+            let _ = std::net::TcpStream::connect(addr);
+        }
+        #[cfg(unix)]
+        ListenAddr::Unix(path) => {
+            let _ = std::os::unix::net::UnixStream::connect(path);
+        }
+        #[cfg(not(unix))]
+        ListenAddr::Unix(_) => (),
+    }
+}
@@ -1,22 +1,212 @@
+use super::super::map::{render, Map, MapPlaces};
+use super::request::{self, Query, Reply, REQUEST_TIMEOUT};
+use super::transport::{Connection, Writer};
+use super::Message;
 use colored::*;
+use std::collections::HashMap;
 use std::io::prelude::*;
 use std::io::BufReader;
-use std::net::TcpStream;
+use std::sync::{mpsc, Arc, Mutex};
 
 #[doc(inline)]
 pub use super::super::error::Result;
 
+/// size of a tile in rendered images, in pixels
+pub const TILE_SIZE: usize = 16;
+
+/// number of rendered images kept in the cache
+///
+/// Random maps create a new image for every seed, so the cache is cleared
+/// when it is full.
+pub const MAX_CACHED_IMAGES: usize = 256;
+
+/// image formats maps are rendered in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// scalable vector graphic, one rect per tile
+    SVG,
+
+    /// uncompressed png image
+    PNG,
+}
+
+impl ImageFormat {
+    /// returns the format for the extension of a requested file
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "svg" => Some(ImageFormat::SVG),
+            "png" => Some(ImageFormat::PNG),
+            _ => None,
+        }
+    }
+
+    /// returns the mime type of the format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::SVG => "image/svg+xml",
+            ImageFormat::PNG => "image/png",
+        }
+    }
+
+    /// renders the map in this format
+    pub fn render(self, map: &Map) -> Vec<u8> {
+        match self {
+            ImageFormat::SVG => render::svg(map, TILE_SIZE).into_bytes(),
+            ImageFormat::PNG => render::png(map, TILE_SIZE),
+        }
+    }
+}
+
+/// rendered images by map digest and format
+type Cache = HashMap<(u64, ImageFormat), Arc<Vec<u8>>>;
+
+/// Thumbnails caches rendered maps by the digest of the map
+///
+/// A map is only rendered again if its tiles changed, so repeated requests
+/// only load the map.
+#[derive(Default)]
+pub struct Thumbnails {
+    /// rendered images
+    p_cache: Mutex<Cache>,
+}
+
+impl Thumbnails {
+    /// creates an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// returns the rendered map, rendering it if it is not in the cache
+    pub fn get(&self, map: &Map, format: ImageFormat) -> Arc<Vec<u8>> {
+        let key = (map.digest(), format);
+        if let Some(image) = self.lock().get(&key) {
+            return Arc::clone(image);
+        }
+
+        // render without holding the lock
+        let image = Arc::new(format.render(map));
+        let mut cache = self.lock();
+        if cache.len() >= MAX_CACHED_IMAGES {
+            cache.clear();
+        }
+        cache.insert(key, Arc::clone(&image));
+        image
+    }
+
+    /// returns the number of cached images
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// returns true if no image is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// locks the cache, a poisoned lock only means a render panicked
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cache> {
+        self.p_cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
 /// This function is called when the client protocol seems to be HTTP
 ///
-/// # Returns
-/// The function flushes the stream to ensure that all data is written,
-/// and then is returning the TcpStream in a Result Box.
+/// # Routes
+/// | Path                  | Content                          |
+/// |-----------------------|----------------------------------|
+/// | `/`                   | html page                        |
+/// | `/maps/{name}.svg`    | map rendered as svg              |
+/// | `/maps/{name}.png`    | map rendered as png              |
+/// | `/maps/{name}/report` | load report of the map as json   |
+/// | `/status`             | counters of the server as json   |
+/// | `/status/clients`     | identified clients as json       |
+/// | `/status/rooms`       | members of every room as json    |
+///
+/// `{name}` is the url encoded name of the map, random maps take the seed
+/// after a space: `/maps/random%2042.png`. The report is answered with
+/// `404 Not Found` if the map could not be loaded, the body tells why.
+///
+/// The response is written with one write of `writer`, after the headers of
+/// the request were read from `reader`. With `verbose` the request is logged.
 pub fn handle_client(
-    stream: &mut TcpStream,
-    _reader: BufReader<TcpStream>,
-) -> Result<&mut TcpStream> {
-    println!("Client {} requestd {}", stream.peer_addr()?, "http".blue());
-    let html_content = r#"
+    writer: &Writer,
+    mut reader: BufReader<Connection>,
+    request: &str,
+    maps: &MapPlaces,
+    thumbnails: &Thumbnails,
+    coordinator: &mpsc::Sender<Message>,
+    verbose: bool,
+) -> Result<()> {
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("/");
+    if verbose {
+        println!(
+            "Client {} requestd {} {}",
+            reader.get_ref().peer(),
+            "http".blue(),
+            path.yellow()
+        );
+    }
+
+    // skip the headers, the body of a GET request is empty
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+        line.clear();
+    }
+
+    if method != "GET" {
+        respond(
+            writer,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"method not allowed\n",
+        )?;
+    } else if path == "/" {
+        respond(
+            writer,
+            "418 I'M A Pokemon",
+            "text/html; charset=utf-8",
+            INDEX.as_bytes(),
+        )?;
+    } else if let Some((map, format)) = image_path(path) {
+        match maps.get(&map) {
+            Ok(map) => {
+                let image = thumbnails.get(&map, format);
+                respond(writer, "200 OK", format.content_type(), &image)?;
+            }
+            Err(_) => respond(writer, "404 Not Found", "text/plain", b"map not found\n")?,
+        }
+    } else if let Some(map) = report_path(path) {
+        let (loaded, report) = maps.get_with_report(&map);
+        let status = match loaded {
+            Ok(_) => "200 OK",
+            Err(_) => "404 Not Found",
+        };
+        let mut json = serde_json::to_string(&report)
+            .unwrap_or_else(|err| format!("{{\"err\": \"{}\"}}", err));
+        json.push('\n');
+        respond(writer, status, "application/json", json.as_bytes())?;
+    } else if let Some(query) = status_query(path) {
+        match request::ask(coordinator, "http", query, REQUEST_TIMEOUT) {
+            Ok(reply) => respond(writer, "200 OK", "application/json", &status_json(reply))?,
+            Err(_) => respond(
+                writer,
+                "503 Service Unavailable",
+                "text/plain",
+                b"coordinator not answering\n",
+            )?,
+        }
+    } else {
+        respond(writer, "404 Not Found", "text/plain", b"not found\n")?;
+    }
+    Ok(())
+}
+
+/// html content of the index page
+const INDEX: &str = r#"
 <!DOCTYPE html>
 <html>
     <head>
@@ -28,17 +218,98 @@ pub fn handle_client(
     </body>
 </html>"#;
 
-    let content = format!(
-        r#"HTTP/1.1 418 I'M A Pokemon
-Server: PokémonEscape server
-Content-Type: text/html; charset=utf-8
-Content-Length: {}
-
-{}"#,
-        html_content.len(),
-        html_content
+/// writes a response with the given status and body
+fn respond(writer: &Writer, status: &str, content_type: &str, body: &[u8]) -> Result<()> {
+    let header = format!(
+        "HTTP/1.1 {}\r\nServer: PokémonEscape server\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
     );
-    stream.write(content.as_bytes())?;
-    stream.flush()?;
-    Ok(stream)
+    let mut response = header.into_bytes();
+    response.extend_from_slice(body);
+    writer.write_all(&response)?;
+    Ok(())
+}
+
+/// returns the query answered by a `/status` path
+pub fn status_query(path: &str) -> Option<Query> {
+    match path.trim_end_matches('/') {
+        "/status" => Some(Query::Stats),
+        "/status/clients" => Some(Query::Clients),
+        "/status/rooms" => Some(Query::Rooms),
+        _ => None,
+    }
+}
+
+/// formats the reply to a status query as json
+fn status_json(reply: Reply) -> Vec<u8> {
+    let json = match reply {
+        Reply::Stats(stats) => serde_json::to_string(&stats),
+        Reply::Clients(clients) => serde_json::to_string(&clients),
+        Reply::Rooms(rooms) => serde_json::to_string(&rooms),
+    };
+    let mut json = json.unwrap_or_else(|err| format!("{{\"err\": \"{}\"}}", err));
+    json.push('\n');
+    json.into_bytes()
+}
+
+/// splits `/maps/{name}.{svg,png}` into the decoded name and the image format
+///
+/// # Example
+/// ```
+/// use poke_escape_server::server::http::{image_path, ImageFormat};
+/// assert_eq!(
+///     image_path("/maps/Hello%20World.svg"),
+///     Some(("Hello World".to_string(), ImageFormat::SVG))
+/// );
+/// assert_eq!(image_path("/maps/Hello%20World"), None);
+/// ```
+pub fn image_path(path: &str) -> Option<(String, ImageFormat)> {
+    let file = path.strip_prefix("/maps/")?;
+    let dot = file.rfind('.')?;
+    let format = ImageFormat::from_extension(&file[dot + 1..])?;
+    let name = decode(&file[..dot])?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, format))
+}
+
+/// returns the decoded name of a `/maps/{name}/report` path
+///
+/// # Example
+/// ```
+/// use poke_escape_server::server::http::report_path;
+/// assert_eq!(
+///     report_path("/maps/random%2042/report"),
+///     Some("random 42".to_string())
+/// );
+/// assert_eq!(report_path("/maps/report"), None);
+/// ```
+pub fn report_path(path: &str) -> Option<String> {
+    let name = path.strip_prefix("/maps/")?.strip_suffix("/report")?;
+    let name = decode(name)?;
+    if name.is_empty() {
+        return None;
+    }
+    Some(name)
+}
+
+/// decodes the `%XX` escapes of an url path
+fn decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = path.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
 }
@@ -0,0 +1,267 @@
+use serde_derive::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use super::super::error::{Error, ErrorKind};
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// prefix of unix domain socket paths in listen addresses
+pub const UNIX_PREFIX: &str = "unix:";
+
+/// ListenAddr is one address the server accepts clients on
+///
+/// # Format
+/// | Address                 | Listener                                 |
+/// |-------------------------|------------------------------------------|
+/// | `127.0.0.1:1996`        | tcp over IPv4                            |
+/// | `[::1]:1996`            | tcp over IPv6                            |
+/// | `[::]:1996`             | tcp over IPv6, dual-stack where the system allows it |
+/// | `unix:/run/poke.sock`   | unix domain socket                       |
+///
+/// # Example
+/// ```
+/// use poke_escape_server::server::listener::ListenAddr;
+/// let addr: ListenAddr = "unix:/tmp/poke.sock".parse().unwrap();
+/// assert_eq!(addr, ListenAddr::Unix("/tmp/poke.sock".into()));
+/// assert_eq!(addr.to_string(), "unix:/tmp/poke.sock");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ListenAddr {
+    /// `host:port` of a tcp socket
+    Tcp(String),
+
+    /// path of a unix domain socket
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = Error;
+
+    /// parses an address, see the format table of `ListenAddr`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if let Some(path) = s.strip_prefix(UNIX_PREFIX) {
+            if path.is_empty() {
+                return Err(Error::new(ErrorKind::NotParsable(format!(
+                    "listen address {}: missing socket path",
+                    s
+                ))));
+            }
+            return Ok(ListenAddr::Unix(PathBuf::from(path)));
+        }
+        // the port is after the last colon, IPv6 hosts are in brackets
+        match s.rfind(':') {
+            Some(colon) if s[colon + 1..].parse::<u16>().is_ok() && colon > 0 => {
+                Ok(ListenAddr::Tcp(s.to_string()))
+            }
+            _ => Err(Error::new(ErrorKind::NotParsable(format!(
+                "listen address {}: expected host:port or unix:path",
+                s
+            )))),
+        }
+    }
+}
+
+impl TryFrom<String> for ListenAddr {
+    type Error = Error;
+
+    /// parses an address read by serde
+    fn try_from(s: String) -> Result<Self> {
+        Self::from_str(&s)
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    /// standart formater for print! macro
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "{}", addr),
+            ListenAddr::Unix(path) => write!(f, "{}{}", UNIX_PREFIX, path.display()),
+        }
+    }
+}
+
+/// Listener is a bound socket accepting clients
+pub enum Listener {
+    /// tcp socket
+    Tcp(TcpListener),
+
+    /// unix domain socket
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// binds the address
+    ///
+    /// A socket left over at the path of a unix domain socket is removed if no
+    /// server answers on it anymore, so a restarted server can bind again.
+    ///
+    /// # Errors
+    /// Returns `IoAddrInUse` if another server still listens on the path.
+    pub fn bind(addr: &ListenAddr) -> Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => Ok(Listener::Tcp(TcpListener::bind(addr)?)),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+                if let Ok(meta) = std::fs::symlink_metadata(path) {
+                    if meta.file_type().is_socket() {
+                        if UnixStream::connect(path).is_ok() {
+                            return Err(Error::from(io::ErrorKind::AddrInUse));
+                        }
+                        std::fs::remove_file(path)?; // stale, nobody answered
+                    }
+                }
+                Ok(Listener::Unix(UnixListener::bind(path)?, path.clone()))
+            }
+            #[cfg(not(unix))]
+            ListenAddr::Unix(path) => Err(Error::new(ErrorKind::NotParsable(format!(
+                "unix sockets are not supported on this system: {}",
+                path.display()
+            )))),
+        }
+    }
+
+    /// returns the address the listener is bound to
+    ///
+    /// For tcp the port is the real port, also when binding port 0.
+    pub fn local_addr(&self) -> Result<ListenAddr> {
+        match self {
+            Listener::Tcp(listener) => Ok(ListenAddr::Tcp(listener.local_addr()?.to_string())),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => Ok(ListenAddr::Unix(path.clone())),
+        }
+    }
+
+    /// waits for the next client
+    pub fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Tcp(listener) => Ok(Stream::Tcp(listener.accept()?.0)),
+            #[cfg(unix)]
+            Listener::Unix(listener, path) => Ok(Stream::Unix(listener.accept()?.0, path.clone())),
+        }
+    }
+}
+
+/// Stream is the connection to one client
+pub enum Stream {
+    /// tcp connection
+    Tcp(TcpStream),
+
+    /// unix domain socket connection, with the path of the listener
+    #[cfg(unix)]
+    Unix(UnixStream, PathBuf),
+}
+
+impl Stream {
+    /// connects to a server listening on the address
+    pub fn connect(addr: &ListenAddr) -> io::Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => Ok(Stream::Tcp(TcpStream::connect(addr)?)),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => Ok(Stream::Unix(UnixStream::connect(path)?, path.clone())),
+            #[cfg(not(unix))]
+            ListenAddr::Unix(_) => Err(io::ErrorKind::Unsupported.into()),
+        }
+    }
+
+    /// returns a second handle to the same connection
+    pub fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Stream::Tcp(stream) => Ok(Stream::Tcp(stream.try_clone()?)),
+            #[cfg(unix)]
+            Stream::Unix(stream, path) => Ok(Stream::Unix(stream.try_clone()?, path.clone())),
+        }
+    }
+
+    /// returns a description of the client for the log
+    ///
+    /// Clients of unix domain sockets have no address, the socket path is used.
+    pub fn peer(&self) -> String {
+        match self {
+            Stream::Tcp(stream) => match stream.peer_addr() {
+                Ok(addr) => addr.to_string(),
+                Err(_) => "unknown".to_string(),
+            },
+            #[cfg(unix)]
+            Stream::Unix(_, path) => format!("{}{}", UNIX_PREFIX, path.display()),
+        }
+    }
+
+    /// closes both directions of the connection
+    ///
+    /// Reads blocked on another handle of the connection return at once.
+    pub fn shutdown(&self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Stream::Unix(stream, _) => stream.shutdown(Shutdown::Both),
+        }
+    }
+
+    /// sets the time to wait for data, `None` waits forever
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream, _) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    /// sets the time to wait while writing, `None` waits forever
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream, _) => stream.set_write_timeout(timeout),
+        }
+    }
+}
+
+impl fmt::Debug for Stream {
+    /// shows the peer of the connection
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Stream({})", self.peer())
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream, _) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream, _) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream, _) => stream.flush(),
+        }
+    }
+}
@@ -1,11 +1,13 @@
+use super::map::report::LoadReport;
+use super::map::store::MapStore;
 use super::map::MapPlaces;
 use colored::*;
 use semver::{Version, VersionReq};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::prelude::*;
 use std::io::BufReader;
-use std::net::TcpStream;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
 
 use super::error::Error;
 
@@ -15,84 +17,156 @@ pub use super::error::Result;
 /// handling code for the http server
 pub mod http;
 
+/// listening sockets and the connections of the clients
+pub mod listener;
+
+/// stopping the server without dropping the clients
+pub mod shutdown;
+
+/// server running in the background, controlled with a handle
+pub mod handle;
+
+/// connections sessions run over, independent of sockets
+pub mod transport;
+
+/// questions to the coordinator, answered on their own channel
+pub mod request;
+
+/// rooms running on their own threads
+pub mod room;
+
+use request::{ClientInfo, Query, Reply, Stats};
+use room::Room;
+use transport::{Connection, Writer};
+
+// tests as sub module
+#[cfg(test)] // only add when running tests
+mod test;
+
+/// newest version of the protocol the server speaks
+///
+/// Clients speaking 0.2.0 or newer get the room events and the shutdown
+/// notice, older clients only get answers to their commands.
+pub const PROTOCOL_VERSION: &str = "0.2.0";
+
 /// This function negotiates the protocoll to use between the client and the Server
-/// it calles the function of the protocoll, uses &TcpStream and a buffer as arguments
-pub fn negotiate(mut conf: Job) -> Result<()> {
+/// it calles the function of the protocoll, uses the connection and a buffer as arguments
+pub fn negotiate(conf: Job) -> Result<()> {
     // FIXME: return error
-    let mut reader = BufReader::new(conf.stream.try_clone()?);
+    let mut reader = BufReader::new(conf.stream);
     let mut line = String::new();
     reader.read_line(&mut line)?;
     if conf.verbose {
-        let addr = conf.stream.peer_addr()?.to_string();
+        let addr = reader.get_ref().peer();
         println!("got {} from {}", line.trim().yellow(), addr.green());
     }
 
-    if line.starts_with("POKE-ESCAPE_") {
-        conf.stream
-            .write(format!("POKE-ESCAPE-SERVER_{}\n", env!("CARGO_PKG_VERSION")).as_bytes())?;
+    if let Some(version) = line.strip_prefix("POKE-ESCAPE_") {
+        conf.writer
+            .write_line(&format!("POKE-ESCAPE-SERVER_{}", env!("CARGO_PKG_VERSION")))?;
         // parse version of client
-        let clientv = Version::parse(&line[12..])?;
+        let clientv = Version::parse(version)?;
 
         if conf.verbose {
             println!("Client with version {} connected", clientv);
         }
 
         // compare version of client
-        let requirment = VersionReq::parse("<= 0.1.0").unwrap();
+        let requirment = VersionReq::parse(&format!("<= {}", PROTOCOL_VERSION)).unwrap();
 
         if requirment.matches(&clientv) {
-            handle_pokemon_client(conf.stream.try_clone()?, conf.sender)?;
+            // older clients would take an event for the answer of a command
+            let current = VersionReq::parse(">= 0.2.0").unwrap().matches(&clientv);
+            if current {
+                conf.writer.accept_notices();
+            }
+            handle_pokemon_client(
+                reader,
+                &conf.writer,
+                conf.sender,
+                &conf.maps,
+                conf.verbose,
+                current,
+            )?;
         } else {
-            conf.stream.write(b"Protocol mismatch.\n")?;
+            conf.writer.write_line("Protocol mismatch.")?;
         }
     } else if line.contains("HTTP/1.1") {
-        http::handle_client(&mut conf.stream, reader)?;
+        http::handle_client(
+            &conf.writer,
+            reader,
+            &line,
+            &conf.maps.current(),
+            &conf.thumbnails,
+            &conf.sender,
+            conf.verbose,
+        )?;
     } else {
-        conf.stream.write(b"Protocol mismatch.\n")?;
+        conf.writer.write_line("Protocol mismatch.")?;
     }
-    conf.stream.flush()?;
     Ok(()) // return type
 }
 
 /// starts the connection to the client
+///
+/// `reader` is the reader used for the handshake, so commands sent together
+/// with the handshake are not lost. Answers are written with `writer`, which
+/// the rooms use for the events of the client too. With `verbose` every
+/// command is logged.
+///
+/// Maps are loaded from the maps currently in `maps` on the thread of the
+/// session, the coordinator only counts them, see `MapLoad`.
+///
+/// With `acknowledge`, for clients speaking protocol 0.2.0, every command
+/// is answered: `identify`, `join` and `chat` with `OK` or `Error`. Older
+/// clients only get an answer to a failed `identify`.
+///
+/// An id must not contain whitespace, it is followed by the room name or
+/// the chat message in the events. Joining a room needs an id, chatting
+/// needs a room.
 pub fn handle_pokemon_client(
-    mut stream: TcpStream,
+    mut reader: BufReader<Connection>,
+    writer: &Writer,
     tx: mpsc::Sender<Message>,
-) -> Result<TcpStream> {
-    let mut reader = BufReader::new(stream.try_clone()?);
-
-    // create channel
-    #[allow(non_snake_case)]
-    let (txOwn, rx) = mpsc::channel();
-    #[allow(non_snake_case)]
-    let mut txOwn = Some(txOwn); // encase in Option<T> so it can move out of scope in a controlled way
-
+    maps: &MapStore,
+    verbose: bool,
+    acknowledge: bool,
+) -> Result<()> {
+    let mut identified = false;
+    let mut joined = false;
     let mut message = Message::empty();
 
     loop {
         // create empty buffer for recieved line
         let mut line = String::new();
 
+        // read from client
         match reader.read_line(&mut line) {
-            // read from client
+            Ok(0) => {
+                // connection closed by the client or on shutdown
+                if identified {
+                    tx.send(message.new_message(MessageBody::CLOSE))?;
+                }
+                return Ok(());
+            }
+            Ok(_) => (),
             Err(_err) => {
                 return Err(Error::new_field_not_exists(
                     "fix error handling".to_string(),
-                ))
-            } //FIXME: return error?
-            Ok(_) => (), // would return usize with number read bytes
-        };
+                )); //FIXME: return error?
+            }
+        }
 
         line = line.trim().to_string();
 
-        println!("read {}", line);
-
-        //stream.write(line.as_bytes())?;
-        stream.flush()?;
+        if verbose {
+            let addr = reader.get_ref().peer();
+            println!("got {} from {}", line.yellow(), addr.green());
+        }
 
         if line.to_lowercase().starts_with("quit") {
             // send quit
-            stream.write(b"Bye\n")?;
+            writer.write_line("Bye")?;
             tx.send(message.new_message(MessageBody::CLOSE))?;
             break; // exit loop
         } else if line.to_lowercase().starts_with("identify") {
@@ -101,53 +175,83 @@ pub fn handle_pokemon_client(
                 tx.send(Message::IDENTIFY(Ident::new(id, txOwn))).unwrap();
                 isIdentified = true;
             }*/
-            match txOwn {
-                Some(tx_o) => {
-                    let id = line[9..].to_string();
-                    message = Message::new_id(id.clone());
-                    tx.send(message.new_message(MessageBody::IDENTIFY(Ident::new(id, tx_o))))?;
-                    //tx.send(Message::IDENTIFY(Ident::new(id, txO))).unwrap();
-                    txOwn = None;
-                }
-                None => {
-                    stream.write(b"Error\n")?;
+            let id = line.get(9..).unwrap_or_default().to_string();
+            if identified || id.is_empty() || id.contains(char::is_whitespace) {
+                writer.write_line("Error")?;
+            } else {
+                message = Message::new_id(id.clone());
+                let ident = Ident::new(id).with_writer(writer.clone());
+                tx.send(message.new_message(MessageBody::IDENTIFY(ident)))?;
+                identified = true;
+                if acknowledge {
+                    writer.write_line("OK")?;
                 }
             }
         } else if line.to_lowercase().starts_with("join") {
-            let group = line[5..].to_string();
-            tx.send(message.new_message(MessageBody::AttachToGroup(group)))?;
+            let group = line.get(5..).unwrap_or_default().to_string();
+            let accepted = identified && !group.is_empty();
+            if accepted {
+                tx.send(message.new_message(MessageBody::AttachToGroup(group)))?;
+                joined = true;
+            }
+            if acknowledge {
+                writer.write_line(if accepted { "OK" } else { "Error" })?;
+            }
+        } else if line.to_lowercase().starts_with("chat") {
+            let text = line.get(5..).unwrap_or_default().to_string();
+            let accepted = joined && !text.is_empty();
+            if accepted {
+                tx.send(message.new_message(MessageBody::Chat(text)))?;
+            }
+            if acknowledge {
+                writer.write_line(if accepted { "OK" } else { "Error" })?;
+            }
         } else if line.to_lowercase().starts_with("map") {
-            let map = line[4..].to_string();
-            tx.send(message.new_message(MessageBody::GetMap(map)))?;
-            let msg = rx.recv()?;
-            match msg.message {
-                MessageBody::Err(_err) => {
-                    stream.write(b"error could not load map\n")?;
-                }
-                MessageBody::Map(map) => {
-                    stream.write(format!("map {}\n", map).as_bytes())?;
-                }
-                _ => {}
+            let name = line.get(4..).unwrap_or_default().to_string();
+            let current = maps.current();
+            let (loaded, report) = current.get_with_report(&name);
+            let (answer, error) = match loaded {
+                Ok(map) => (format!("map {}", map), None),
+                Err(err) => (
+                    "error could not load map".to_string(),
+                    Some(err.to_string()),
+                ),
+            };
+            let load = MapLoad {
+                maps: current,
+                name,
+                error,
+                report,
             };
-            stream.flush()?;
+            // counted before the answer, so a client sees its maps in the stats
+            tx.send(message.new_message(MessageBody::MapLoaded(load)))?;
+            writer.write_line(&answer)?;
         } else {
-            stream.write(b"Unknown command\n")?;
+            writer.write_line("Unknown command")?;
         }
     }
-    stream.flush()?;
-    Ok(stream)
+    Ok(())
 }
 
-/// Job as parameter for negotiate to give the TCPStream and the verbose state
+/// Job as parameter for negotiate to give the connection and the verbose state
 pub struct Job {
-    /// TcpStream of the client
-    pub stream: TcpStream,
+    /// connection of the client, only read from
+    pub stream: Connection,
+
+    /// writes to the connection of the client, see `transport::Writer`
+    pub writer: Writer,
 
     /// verbose state
     pub verbose: bool,
 
     /// channel to communicate with scheduler
     pub sender: mpsc::Sender<Message>,
+
+    /// maps loaded for the clients and rendered for http clients
+    pub maps: Arc<MapStore>,
+
+    /// cache of rendered maps, shared by all http clients
+    pub thumbnails: Arc<http::Thumbnails>,
 }
 
 /// struct for the identification of the client
@@ -156,14 +260,20 @@ pub struct Ident {
     /// id of the client (generated as UUID)
     pub id: String,
 
-    /// channel to send messages to
-    pub tx: mpsc::Sender<Message>,
+    /// writer of the client, used for the events of its room
+    pub writer: Option<Writer>,
 }
 
 impl Ident {
     /// create a new instance of Ident
-    pub fn new(id: String, tx: mpsc::Sender<Message>) -> Self {
-        Ident { id, tx }
+    pub fn new(id: String) -> Self {
+        Ident { id, writer: None }
+    }
+
+    /// sets the writer the events of the client are written with
+    pub fn with_writer(mut self, writer: Writer) -> Self {
+        self.writer = Some(writer);
+        self
     }
 }
 
@@ -228,15 +338,41 @@ pub enum MessageBody {
     /// Command to attach to group
     AttachToGroup(String),
 
-    /// send map to client
-    GetMap(String),
+    /// chat message for the other members of the room of the client
+    Chat(String),
 
-    /// loaded map to return to client
-    Map(String),
+    /// a map the client loaded, for the counters of the coordinator
+    MapLoaded(MapLoad),
 
-    /// error with error description
-    /// used for example if map could not be loaded
-    Err(String),
+    /// question to the coordinator, the reply is sent on the channel
+    ///
+    /// See `request::ask`.
+    Request(Query, mpsc::SyncSender<Reply>),
+}
+
+/// MapLoad tells the coordinator about a map a session loaded
+pub struct MapLoad {
+    /// maps the map was loaded from
+    pub maps: Arc<MapPlaces>,
+
+    /// name the client asked for
+    pub name: String,
+
+    /// why the map could not be loaded, `None` if it was loaded
+    pub error: Option<String>,
+
+    /// report of loading the map
+    pub report: LoadReport,
+}
+
+impl std::fmt::Debug for MapLoad {
+    /// shows the map and whether it was loaded
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.error {
+            None => write!(f, "MapLoad({})", self.name),
+            Some(err) => write!(f, "MapLoad({}: {})", self.name, err),
+        }
+    }
 }
 
 /// struct used in hashmap of the coordinator
@@ -244,26 +380,79 @@ struct Client {
     /// room of the client
     pub room: Option<String>,
 
-    /// sender for the client
-    pub tx: mpsc::Sender<Message>,
+    /// writer of the client for the events of its room
+    pub writer: Option<Writer>,
 }
 
 impl Client {
     /// create a new instance of the client
-    pub fn new(tx: mpsc::Sender<Message>) -> Self {
-        Self { room: None, tx }
+    pub fn new() -> Self {
+        Self {
+            room: None,
+            writer: None,
+        }
+    }
+}
+
+/// removes the client from the room, stops the room after its last member
+///
+/// The thread of a stopped room is kept in `stopping` until it wrote its
+/// last events, a new room with the same name waits for it.
+fn leave(
+    rooms: &mut HashMap<String, Room>,
+    stopping: &mut HashMap<String, JoinHandle<()>>,
+    room: &str,
+    id: &str,
+) {
+    if let Some(handle) = rooms.get_mut(room) {
+        handle.leave(id);
+        if handle.is_empty() {
+            stopping.retain(|_, thread| !thread.is_finished());
+            if let Some(thread) = rooms.remove(room).and_then(Room::stop) {
+                stopping.insert(room.to_string(), thread);
+            }
+        }
     }
 }
 
 /// handle interclient communication
-pub fn server_client(rx: mpsc::Receiver<Message>, verbose: u8, maps: MapPlaces) {
+///
+/// Members of a room get `event join <id> <room>` when a client joins the
+/// room and `event leave <id> <room>` when a client leaves it, by joining
+/// another room or by quitting. A chat message of a client is passed on to
+/// the other members as `event chat <id> <text>`. Only members speaking
+/// protocol 0.2.0 get the events, see `PROTOCOL_VERSION`.
+///
+/// The coordinator only keeps a directory of the clients and their rooms and
+/// routes them, every room runs as its own `room::Room` on its own thread.
+/// A room is started with its first member and stopped with its last. The
+/// events of a room keep their order, also when it is started again.
+///
+/// Maps are loaded by the sessions, the coordinator only counts them. With a
+/// `verbose` level of at least 1 the load report of a map is printed the
+/// first time it is loaded from the maps currently in `maps`, and again
+/// after a reload.
+///
+/// Requests are answered on the channel they carry, see `request::ask`.
+pub fn server_client(rx: mpsc::Receiver<Message>, verbose: u8, maps: Arc<MapStore>) {
     std::thread::spawn(move || {
         // hashmap containing the induvidual clients
         let mut clients: HashMap<String, Client> = HashMap::new();
 
-        // hashmap containing the group of clients
-        let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+        // rooms with at least one member
+        let mut rooms: HashMap<String, Room> = HashMap::new();
+
+        // threads of stopped rooms still writing their last events
+        let mut stopping: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+        // counters answered to `Query::Stats`
+        let started = std::time::Instant::now();
+        let mut stats = Stats::default();
+
+        // maps whose load report was printed, for the maps in `reported.0`
+        let mut reported = (maps.current(), HashSet::new());
         for recv in rx {
+            stats.messages += 1;
             match recv.message {
                 MessageBody::CLOSE => {
                     match clients.remove(&recv.id) {
@@ -272,94 +461,121 @@ pub fn server_client(rx: mpsc::Receiver<Message>, verbose: u8, maps: MapPlaces)
                                 println!("debug2: removing client {}", recv.id);
                             }
                             // remove client from group
-                            match client.room {
-                                Some(room) => {
-                                    match groups.get_mut(&room) {
-                                        Some(group) => {
-                                            if verbose >= 3 {
-                                                println!(
-                                                    "debug3: remove client from group {}",
-                                                    room
-                                                );
-                                            }
-                                            group.retain(|x| x != &recv.id); // remove user from group
-                                        }
-                                        None => (),
-                                    }
+                            if let Some(room) = client.room {
+                                if verbose >= 3 {
+                                    println!("debug3: remove client from group {}", room);
                                 }
-                                None => (),
+                                leave(&mut rooms, &mut stopping, &room, &recv.id);
+                            }
+                        }
+                        None => {
+                            if verbose >= 2 {
+                                println!("debug2: client {} was not identified", recv.id);
                             }
                         }
-                        None => eprint!("Already deletet?"),
                     }
                 }
                 MessageBody::IDENTIFY(ident) => {
                     if verbose >= 2 {
                         println!("debug2: client {} identified himself", ident.id);
                     }
-                    clients.insert(ident.id, Client::new(ident.tx));
+                    let mut client = Client::new();
+                    client.writer = ident.writer;
+                    clients.insert(ident.id, client);
                 }
                 MessageBody::AttachToGroup(group) => {
                     if verbose >= 2 {
                         println!("debug2: client {} joind group {}", recv.id, group);
                     }
-                    match clients.get_mut(&recv.id) {
-                        Some(client) => {
-                            client.room = Some(group.clone());
+                    let (old, writer) = match clients.get_mut(&recv.id) {
+                        Some(client) => (client.room.replace(group.clone()), client.writer.clone()),
+                        None => {
+                            if verbose >= 1 {
+                                eprintln!("{} joined without identifying", recv.id);
+                            }
+                            (None, None)
                         }
-                        None => eprintln!("ERROR: {} not in clients database", recv.id),
+                    };
+                    if old.as_ref() == Some(&group) {
+                        continue; // already in the room
                     }
-                    match groups.get_mut(&group) {
-                        Some(group) => {
-                            group.push(recv.id.clone());
-                        }
+                    if let Some(old) = old {
+                        leave(&mut rooms, &mut stopping, &old, &recv.id);
+                    }
+                    rooms
+                        .entry(group.clone())
+                        .or_insert_with(|| Room::spawn(&group, verbose, stopping.remove(&group)))
+                        .join(&recv.id, writer);
+                }
+                MessageBody::Chat(text) => {
+                    let room = clients
+                        .get(&recv.id)
+                        .and_then(|client| client.room.as_ref());
+                    match room.and_then(|room| rooms.get(room)) {
+                        Some(room) => room.chat(&recv.id, &text),
                         None => {
-                            let mut vec = Vec::new();
-                            vec.push(recv.id);
-                            groups.insert(group, vec);
+                            if verbose >= 1 {
+                                eprintln!("{} chatted outside of a room", recv.id);
+                            }
                         }
-                    };
+                    }
                 }
-                MessageBody::GetMap(map) => {
+                MessageBody::MapLoaded(load) => {
                     if verbose >= 2 {
-                        println!("debug2: load map {}", map);
+                        println!("debug2: client {} loaded map {}", recv.id, load.name);
                     }
-                    let channel = match clients.get(&recv.id) {
-                        Some(client) => &client.tx,
-                        None => {
-                            eprintln!("client {} not available to get tx channel", &recv.id);
-                            continue;
-                        }
-                    };
-                    match maps.get(&map) {
-                        Ok(map) => {
-                            // return map as json
-                            channel
-                                .send(Message {
-                                    id: "master".to_string(),
-                                    message: MessageBody::Map(map.to_string()),
-                                })
-                                .unwrap_or_else(|err| {
-                                    eprintln!("could not send map: {}", err);
-                                });
-                        }
-                        Err(err) => {
+                    match load.error {
+                        None => stats.maps_loaded += 1,
+                        Some(err) => {
                             if verbose >= 3 {
                                 eprintln!("debug3: could not load map: {}", err);
                             }
-                            // send error back
-                            channel
-                                .send(Message {
-                                    id: "master".to_string(),
-                                    message: MessageBody::Err("could not load map".to_string()),
+                            stats.map_errors += 1;
+                        }
+                    }
+                    let current = maps.current();
+                    if !Arc::ptr_eq(&current, &reported.0) {
+                        reported = (Arc::clone(&current), HashSet::new());
+                    }
+                    // a map loaded just before a reload is reported after it
+                    if Arc::ptr_eq(&load.maps, &current)
+                        && reported.1.insert(load.name)
+                        && verbose >= 1
+                    {
+                        load.report.print();
+                    }
+                }
+                MessageBody::Request(query, reply) => {
+                    let answer = match query {
+                        Query::Clients => {
+                            let mut list: Vec<ClientInfo> = clients
+                                .iter()
+                                .map(|(id, client)| ClientInfo {
+                                    id: id.clone(),
+                                    room: client.room.clone(),
                                 })
-                                .unwrap_or_else(|err| {
-                                    eprintln!("could not send map error: {}", err)
-                                });
+                                .collect();
+                            list.sort_by(|a, b| a.id.cmp(&b.id));
+                            Reply::Clients(list)
                         }
+                        Query::Rooms => Reply::Rooms(
+                            rooms
+                                .values()
+                                .map(|room| (room.name().to_string(), room.members().to_vec()))
+                                .collect(),
+                        ),
+                        Query::Stats => Reply::Stats(Stats {
+                            clients: clients.len(),
+                            rooms: rooms.len(),
+                            uptime: started.elapsed().as_secs(),
+                            ..stats.clone()
+                        }),
                     };
+                    // the caller may have given up waiting
+                    if reply.try_send(answer).is_err() && verbose >= 3 {
+                        eprintln!("debug3: {} did not wait for the reply", recv.id);
+                    }
                 }
-                _ => eprintln!("Error, could not handel : {:?}", recv.message),
             }
         }
     });
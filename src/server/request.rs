@@ -0,0 +1,97 @@
+use super::super::error::{Error, ErrorKind};
+use super::{Message, MessageBody};
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// time the http side waits for the coordinator
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Query is a question to the coordinator, sent with `MessageBody::Request`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    /// lists the identified clients, answered with `Reply::Clients`
+    Clients,
+
+    /// lists the rooms and their members, answered with `Reply::Rooms`
+    Rooms,
+
+    /// counters of the coordinator, answered with `Reply::Stats`
+    Stats,
+}
+
+/// Reply is the answer of the coordinator to a `Query`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reply {
+    /// identified clients, sorted by id
+    Clients(Vec<ClientInfo>),
+
+    /// members of every room by room name
+    Rooms(BTreeMap<String, Vec<String>>),
+
+    /// counters of the coordinator
+    Stats(Stats),
+}
+
+/// ClientInfo describes one identified client
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ClientInfo {
+    /// id the client identified with
+    pub id: String,
+
+    /// room the client joined
+    pub room: Option<String>,
+}
+
+/// Stats holds the counters of the coordinator
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Stats {
+    /// number of identified clients
+    pub clients: usize,
+
+    /// number of rooms with at least one member
+    pub rooms: usize,
+
+    /// messages handled since the start, including this request
+    pub messages: u64,
+
+    /// maps sent to clients
+    pub maps_loaded: u64,
+
+    /// map requests which failed
+    pub map_errors: u64,
+
+    /// seconds since the coordinator started
+    pub uptime: u64,
+}
+
+/// sends a query to the coordinator and waits for the reply
+///
+/// Every request carries its own reply channel, so callers do not need to
+/// be identified clients. `id` names the caller in the log of the
+/// coordinator.
+///
+/// # Errors
+/// Returns `IoTimedOut` if the coordinator did not answer within `timeout`,
+/// and `ReceiveError` if it stopped.
+pub fn ask(
+    coordinator: &mpsc::Sender<Message>,
+    id: &str,
+    query: Query,
+    timeout: Duration,
+) -> Result<Reply> {
+    let (reply, answer) = mpsc::sync_channel(1);
+    coordinator.send(Message::new(
+        id.to_string(),
+        MessageBody::Request(query, reply),
+    ))?;
+    match answer.recv_timeout(timeout) {
+        Ok(reply) => Ok(reply),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(Error::from(std::io::ErrorKind::TimedOut)),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(Error::new(ErrorKind::ReceiveError)),
+    }
+}
@@ -0,0 +1,174 @@
+use super::transport::Writer;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+/// RoomMessage is handled by the thread of one room
+pub enum RoomMessage {
+    /// a client joined, with the writer its events are written with
+    Join(String, Option<Writer>),
+
+    /// a client left
+    Leave(String),
+
+    /// a member sent a chat message
+    Chat(String, String),
+}
+
+/// Room is the handle of a room kept by the coordinator
+///
+/// Every room runs on its own thread with its own queue, so writing the
+/// events of a busy room or to a slow client does not hold up the other
+/// rooms. The handle keeps the ids of the members for routing and queries,
+/// the thread keeps their writers. Dropping the handle stops the thread
+/// after the queued messages were handled.
+pub struct Room {
+    /// name of the room
+    p_name: String,
+
+    /// ids of the members, in the order they joined
+    p_members: Vec<String>,
+
+    /// queue of the room thread
+    p_tx: mpsc::Sender<RoomMessage>,
+
+    /// thread of the room, None if it could not be started
+    p_thread: Option<JoinHandle<()>>,
+}
+
+impl Room {
+    /// starts the thread of a new, empty room
+    ///
+    /// `previous` is the thread of the stopped room with the same name, see
+    /// `stop`. The new thread waits for it, so the members never get the
+    /// events of the new room before the last events of the old one.
+    pub fn spawn(name: &str, verbose: u8, previous: Option<JoinHandle<()>>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let room = name.to_string();
+        let spawned = thread::Builder::new()
+            .name(format!("room {}", name))
+            .spawn(move || {
+                if let Some(previous) = previous {
+                    let _ = previous.join();
+                }
+                run(&room, rx, verbose)
+            });
+        let thread = match spawned {
+            Ok(thread) => Some(thread),
+            Err(err) => {
+                // the messages are dropped, the clients only miss events
+                eprintln!("could not start thread of room {}: {}", name, err);
+                None
+            }
+        };
+        Room {
+            p_name: name.to_string(),
+            p_members: Vec::new(),
+            p_tx: tx,
+            p_thread: thread,
+        }
+    }
+
+    /// stops the room, returns its thread still handling the queued messages
+    pub fn stop(self) -> Option<JoinHandle<()>> {
+        self.p_thread
+    }
+
+    /// returns the name of the room
+    pub fn name(&self) -> &str {
+        &self.p_name
+    }
+
+    /// returns the ids of the members, in the order they joined
+    pub fn members(&self) -> &[String] {
+        &self.p_members
+    }
+
+    /// returns true if the last member left
+    pub fn is_empty(&self) -> bool {
+        self.p_members.is_empty()
+    }
+
+    /// adds a client, the members get `event join <id> <room>`
+    pub fn join(&mut self, id: &str, writer: Option<Writer>) {
+        self.p_members.push(id.to_string());
+        self.send(RoomMessage::Join(id.to_string(), writer));
+    }
+
+    /// removes a client, the members left get `event leave <id> <room>`
+    pub fn leave(&mut self, id: &str) {
+        self.p_members.retain(|x| x != id);
+        self.send(RoomMessage::Leave(id.to_string()));
+    }
+
+    /// passes on a chat message, the other members get `event chat <id> <text>`
+    pub fn chat(&self, id: &str, text: &str) {
+        self.send(RoomMessage::Chat(id.to_string(), text.to_string()));
+    }
+
+    /// queues a message for the room thread
+    fn send(&self, message: RoomMessage) {
+        if self.p_tx.send(message).is_err() {
+            eprintln!("room {} stopped", self.p_name);
+        }
+    }
+}
+
+/// handles the messages of one room until its handle is dropped
+fn run(room: &str, rx: mpsc::Receiver<RoomMessage>, verbose: u8) {
+    let mut members: Vec<(String, Option<Writer>)> = Vec::new();
+    for message in rx {
+        match message {
+            RoomMessage::Join(id, writer) => {
+                if verbose >= 3 {
+                    println!("debug3: room {}: {} joined", room, id);
+                }
+                notify(
+                    &members,
+                    None,
+                    &format!("event join {} {}", id, room),
+                    verbose,
+                );
+                members.push((id, writer));
+            }
+            RoomMessage::Leave(id) => {
+                if verbose >= 3 {
+                    println!("debug3: room {}: {} left", room, id);
+                }
+                members.retain(|(member, _)| member != &id);
+                notify(
+                    &members,
+                    None,
+                    &format!("event leave {} {}", id, room),
+                    verbose,
+                );
+            }
+            RoomMessage::Chat(id, text) => {
+                if verbose >= 3 {
+                    println!("debug3: room {}: {} chats", room, id);
+                }
+                let line = format!("event chat {} {}", id, text);
+                notify(&members, Some(&id), &line, verbose);
+            }
+        }
+    }
+}
+
+/// writes the line to every member of a room accepting notices
+///
+/// The member `except` is skipped, see `Writer::notice`. A member which
+/// cannot be written to is about to leave, its error is only logged with a
+/// `verbose` level of at least 1.
+fn notify(members: &[(String, Option<Writer>)], except: Option<&str>, line: &str, verbose: u8) {
+    for (member, writer) in members {
+        if Some(member.as_str()) == except {
+            continue;
+        }
+        if let Some(writer) = writer {
+            if let Err(err) = writer.notice(line) {
+                if verbose >= 1 {
+                    eprintln!("could not notify {}: {}", member, err);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::transport::{Connection, Transport, Writer};
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[doc(inline)]
+pub use super::super::error::Result;
+
+/// Event is handled by the accept loop of `Config::run`
+pub enum Event {
+    /// a client connected to one of the listeners
+    Client(io::Result<Connection>),
+
+    /// stop accepting and shut the server down, with the reason for the log
+    Stop(String),
+}
+
+/// Sessions keeps a handle to the connection of every client being served
+///
+/// On shutdown the server notifies every client, waits until every session
+/// ended, and closes the connections left when the deadline is reached.
+#[derive(Default)]
+pub struct Sessions {
+    /// connections and their writers by session id
+    p_streams: Mutex<HashMap<usize, (Connection, Writer)>>,
+
+    /// id of the next session
+    p_next: Mutex<usize>,
+
+    /// signaled when a session ends
+    p_ended: Condvar,
+}
+
+impl Sessions {
+    /// creates an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// registers a connection and its writer, returns the id to remove it with
+    pub fn add(&self, stream: &dyn Transport, writer: Writer) -> io::Result<usize> {
+        let stream = stream.try_clone()?;
+        let id = {
+            let mut next = self.p_next.lock().unwrap_or_else(|p| p.into_inner());
+            *next += 1;
+            *next
+        };
+        self.lock().insert(id, (stream, writer));
+        Ok(id)
+    }
+
+    /// removes the connection after the session ended
+    pub fn remove(&self, id: usize) {
+        self.lock().remove(&id);
+        self.p_ended.notify_all();
+    }
+
+    /// returns the number of sessions being served
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// returns true if no session is served
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// writes the line to every client accepting notices
+    ///
+    /// See `Writer::notice`. Returns the number of clients notified.
+    pub fn notify(&self, line: &str) -> usize {
+        // a slow client must not keep the sessions from ending meanwhile
+        let writers: Vec<Writer> = self.lock().values().map(|(_, w)| w.clone()).collect();
+        writers
+            .iter()
+            .filter(|writer| writer.notice(line).unwrap_or(false))
+            .count()
+    }
+
+    /// waits until every session ended or the timeout passed
+    ///
+    /// Returns true if every session ended in time.
+    pub fn wait_empty(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut streams = self.lock();
+        while !streams.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            streams = match self.p_ended.wait_timeout(streams, deadline - now) {
+                Ok((streams, _)) => streams,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+        true
+    }
+
+    /// closes every connection left, so the blocked sessions end
+    ///
+    /// Returns the number of closed connections.
+    pub fn close_all(&self) -> usize {
+        let streams = self.lock();
+        for (stream, _) in streams.values() {
+            let _ = stream.shutdown();
+        }
+        streams.len()
+    }
+
+    /// locks the connections, a poisoned lock only means a session panicked
+    fn lock(&self) -> MutexGuard<'_, HashMap<usize, (Connection, Writer)>> {
+        self.p_streams.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+/// sends `Event::Stop` into the accept loop on SIGINT and SIGTERM
+///
+/// Spawns a thread waiting for the signals. A second signal exits at once,
+/// without waiting for the clients.
+#[cfg(unix)]
+pub fn stop_on_signals(events: std::sync::mpsc::Sender<Event>) -> Result<()> {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+
+    let mut signals = Signals::new([SIGINT, SIGTERM])?;
+    std::thread::spawn(move || {
+        let mut stopping = false;
+        for signal in signals.forever() {
+            let name = if signal == SIGINT {
+                "SIGINT"
+            } else {
+                "SIGTERM"
+            };
+            if stopping {
+                eprintln!("got {} again, exiting without waiting for clients", name);
+                std::process::exit(1);
+            }
+            stopping = true;
+            if events.send(Event::Stop(name.to_string())).is_err() {
+                std::process::exit(1);
+            }
+        }
+    });
+    Ok(())
+}
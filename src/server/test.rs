@@ -0,0 +1,524 @@
+//! tests for the server, which do not need a running server
+
+mod http {
+    use super::super::http::{image_path, ImageFormat, Thumbnails};
+    use crate::map::generator::{generate, GeneratorSettings};
+
+    #[test]
+    fn image_paths() {
+        assert_eq!(
+            image_path("/maps/random%2042.png"),
+            Some(("random 42".to_string(), ImageFormat::PNG))
+        );
+        assert_eq!(
+            image_path("/maps/v1.2.SVG"),
+            Some(("v1.2".to_string(), ImageFormat::SVG))
+        );
+        assert_eq!(image_path("/maps/.svg"), None);
+        assert_eq!(image_path("/maps/test.gif"), None);
+        assert_eq!(image_path("/maps/bad%zz.svg"), None);
+        assert_eq!(image_path("/other/test.svg"), None);
+    }
+
+    #[test]
+    fn status_paths() {
+        use super::super::http::status_query;
+        use super::super::request::Query;
+        assert_eq!(status_query("/status"), Some(Query::Stats));
+        assert_eq!(status_query("/status/"), Some(Query::Stats));
+        assert_eq!(status_query("/status/clients"), Some(Query::Clients));
+        assert_eq!(status_query("/status/rooms"), Some(Query::Rooms));
+        assert_eq!(status_query("/status/other"), None);
+    }
+
+    #[test]
+    fn thumbnails_are_cached() {
+        let settings = GeneratorSettings::default();
+        let thumbnails = Thumbnails::new();
+        let a = generate("random 1", &settings, 1);
+        let first = thumbnails.get(&a, ImageFormat::PNG);
+        let again = thumbnails.get(&generate("random 1", &settings, 1), ImageFormat::PNG);
+        assert!(std::sync::Arc::ptr_eq(&first, &again));
+        assert_eq!(thumbnails.len(), 1);
+
+        thumbnails.get(&a, ImageFormat::SVG);
+        thumbnails.get(&generate("random 2", &settings, 2), ImageFormat::PNG);
+        assert_eq!(thumbnails.len(), 3);
+    }
+}
+
+mod listener {
+    use super::super::listener::{ListenAddr, Listener, Stream};
+    use crate::error::ErrorKind;
+    use std::io::prelude::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn parse_addresses() {
+        let parse = |s: &str| s.parse::<ListenAddr>();
+        assert_eq!(
+            parse("0.0.0.0:1996").unwrap(),
+            ListenAddr::Tcp("0.0.0.0:1996".to_string())
+        );
+        assert_eq!(
+            parse("[::]:1996").unwrap(),
+            ListenAddr::Tcp("[::]:1996".to_string())
+        );
+        assert_eq!(
+            parse("unix:./poke.sock").unwrap(),
+            ListenAddr::Unix("./poke.sock".into())
+        );
+        assert!(parse("localhost").is_err());
+        assert!(parse(":1996").is_err());
+        assert!(parse("unix:").is_err());
+    }
+
+    /// writes a line from a client and reads it on the server side
+    fn echo(listener: &Listener, client: &mut dyn Write) {
+        client.write_all(b"POKE-ESCAPE_0.1.0\n").unwrap();
+        let stream = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream.try_clone().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        assert_eq!(line, "POKE-ESCAPE_0.1.0\n");
+        assert!(!stream.peer().is_empty());
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = Listener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = match listener.local_addr().unwrap() {
+            ListenAddr::Tcp(addr) => addr,
+            addr => panic!("wrong address {}", addr),
+        };
+        assert!(!addr.ends_with(":0"));
+        let mut client = std::net::TcpStream::connect(addr).unwrap();
+        echo(&listener, &mut client);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix() {
+        let path = std::env::temp_dir().join(format!("poke_escape_{}.sock", std::process::id()));
+        let addr = ListenAddr::Unix(path.clone());
+        let listener = Listener::bind(&addr).unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+        let mut client = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        echo(&listener, &mut client);
+
+        // a running server keeps its socket
+        let err = Listener::bind(&addr).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::IoAddrInUse);
+        drop(listener);
+
+        // a socket left over is replaced
+        let listener = Listener::bind(&addr).unwrap();
+        let _client = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        match listener.accept().unwrap() {
+            Stream::Unix(..) => (),
+            _ => panic!("expected a unix stream"),
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+mod shutdown {
+    use super::super::listener::{Listener, Stream};
+    use super::super::shutdown::Sessions;
+    use super::super::transport::{pipe, Transport, Writer};
+    use std::io::prelude::*;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// returns the server side of a new local connection and the client
+    fn connect(listener: &Listener) -> (Stream, std::net::TcpStream) {
+        let addr = listener.local_addr().unwrap().to_string();
+        let client = std::net::TcpStream::connect(addr).unwrap();
+        (listener.accept().unwrap(), client)
+    }
+
+    #[test]
+    fn wait_for_sessions() {
+        let listener = Listener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let sessions = Arc::new(Sessions::new());
+        let (stream, _client) = connect(&listener);
+        let writer = Writer::new(Box::new(stream.try_clone().unwrap()));
+        let id = sessions.add(&stream, writer).unwrap();
+        assert_eq!(sessions.len(), 1);
+
+        let ending = Arc::clone(&sessions);
+        let session = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            ending.remove(id);
+        });
+        assert!(sessions.wait_empty(Duration::from_secs(5)));
+        assert!(sessions.is_empty());
+        session.join().unwrap();
+    }
+
+    #[test]
+    fn close_after_deadline() {
+        let listener = Listener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        let sessions = Sessions::new();
+        let (mut stream, _client) = connect(&listener);
+        let writer = Writer::new(Box::new(stream.try_clone().unwrap()));
+        sessions.add(&stream, writer).unwrap();
+
+        let start = Instant::now();
+        assert!(!sessions.wait_empty(Duration::from_millis(50)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // the blocked read of the session returns
+        assert_eq!(sessions.close_all(), 1);
+        let mut buf = [0; 8];
+        assert_eq!(stream.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn notify_after_handshake() {
+        let sessions = Sessions::new();
+        let (mut game, game_session) = pipe("game");
+        let (mut http, http_session) = pipe("http");
+        let game_writer = Writer::new(game_session.try_clone().unwrap());
+        game_writer.accept_notices();
+        sessions.add(&game_session, game_writer).unwrap();
+        let http_writer = Writer::new(http_session.try_clone().unwrap());
+        sessions.add(&http_session, http_writer).unwrap();
+
+        assert_eq!(sessions.notify("shutdown 5"), 1);
+        let mut buf = [0; 11];
+        game.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"shutdown 5\n");
+        drop(sessions);
+        drop(http_session);
+        let mut read = String::new();
+        http.read_to_string(&mut read).unwrap();
+        assert_eq!(read, "");
+    }
+}
+
+mod handle {
+    use crate::testing::{config, TempDir};
+    use std::io::prelude::*;
+    use std::io::BufReader;
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    #[test]
+    fn start_and_shutdown() {
+        let dir = TempDir::new("handle").unwrap();
+        let server = config(&dir).unwrap().start().unwrap();
+        let addr = server.local_addrs()[0].to_string();
+        assert!(!addr.ends_with(":0"));
+        assert_eq!(server.state().maps, 1);
+
+        let mut client = TcpStream::connect(&addr).unwrap();
+        client
+            .write_all(b"POKE-ESCAPE_0.2.0\nidentify a\n")
+            .unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "POKE-ESCAPE-SERVER_0.1.0\n");
+        assert_eq!(server.state().clients, 1);
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "OK\n");
+
+        // the map is answered after the coordinator knows the client
+        client.write_all(b"map random 1\n").unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("map {"), "{}", line);
+
+        // the client quits after the notice, so shutdown does not wait
+        let quit = std::thread::spawn(move || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "shutdown 5\n");
+            client.write_all(b"quit\n").unwrap();
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "Bye\n");
+        });
+        let start = std::time::Instant::now();
+        server.shutdown().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        quit.join().unwrap();
+
+        // the listener is closed
+        assert!(TcpStream::connect(&addr).is_err());
+    }
+
+    #[test]
+    fn shutdown_notice_without_identify() {
+        let dir = TempDir::new("handle_notice").unwrap();
+        let server = config(&dir).unwrap().start().unwrap();
+        let addr = server.local_addrs()[0].to_string();
+
+        let mut client = TcpStream::connect(&addr).unwrap();
+        client.write_all(b"POKE-ESCAPE_0.2.0\n").unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "POKE-ESCAPE-SERVER_0.1.0\n");
+        assert_eq!(server.state().clients, 1);
+
+        let quit = std::thread::spawn(move || {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "shutdown 5\n");
+            client.write_all(b"quit\n").unwrap();
+        });
+        let start = std::time::Instant::now();
+        server.shutdown().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        quit.join().unwrap();
+    }
+
+    #[test]
+    fn errors_are_returned() {
+        let dir = TempDir::new("handle_errors").unwrap();
+        let mut missing = config(&dir).unwrap();
+        missing.config = "/does/not/exist.toml".to_string();
+        assert!(missing.start().is_err());
+
+        let first = config(&dir).unwrap().start().unwrap();
+        let mut taken = config(&dir).unwrap();
+        taken.listen = first.local_addrs().to_vec();
+        assert!(taken.start().is_err());
+        assert!(first.state().running);
+    }
+
+    #[test]
+    fn serve_pipe() {
+        use crate::map::store::MapStore;
+        use crate::server::handle::ServerHandle;
+        use crate::server::transport::pipe;
+        use std::sync::Arc;
+
+        let dir = TempDir::new("handle_pipe").unwrap();
+        let config = config(&dir).unwrap();
+        let maps = Arc::new(MapStore::new(&config.config, false).unwrap());
+        let server = ServerHandle::start_on(&config, maps, &[]).unwrap();
+        assert!(server.local_addrs().is_empty());
+
+        let (mut client, connection) = pipe("serve");
+        server.serve(Box::new(connection)).unwrap();
+        client
+            .write_all(b"POKE-ESCAPE_0.1.0\nidentify p\nhello\nquit\n")
+            .unwrap();
+        let mut transcript = String::new();
+        client.read_to_string(&mut transcript).unwrap();
+        assert_eq!(
+            transcript,
+            "POKE-ESCAPE-SERVER_0.1.0\nUnknown command\nBye\n"
+        );
+        server.shutdown().unwrap();
+    }
+
+    #[test]
+    fn query_state() {
+        use crate::server::request::{ClientInfo, Query, Reply};
+
+        let dir = TempDir::new("handle_query").unwrap();
+        let server = config(&dir).unwrap().start().unwrap();
+        let addr = server.local_addrs()[0].to_string();
+        let timeout = Duration::from_secs(5);
+
+        let mut client = TcpStream::connect(&addr).unwrap();
+        client
+            .write_all(b"POKE-ESCAPE_0.1.0\nidentify q\njoin hall\nmap random\nmap nope\n")
+            .unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        for _ in 0..3 {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+        }
+
+        assert_eq!(
+            server.query(Query::Clients, timeout).unwrap(),
+            Reply::Clients(vec![ClientInfo {
+                id: "q".to_string(),
+                room: Some("hall".to_string())
+            }])
+        );
+        match server.query(Query::Rooms, timeout).unwrap() {
+            Reply::Rooms(rooms) => assert_eq!(rooms["hall"], vec!["q".to_string()]),
+            reply => panic!("wrong reply {:?}", reply),
+        }
+        match server.query(Query::Stats, timeout).unwrap() {
+            Reply::Stats(stats) => {
+                assert_eq!(stats.clients, 1);
+                assert_eq!(stats.rooms, 1);
+                assert_eq!(stats.maps_loaded, 1);
+                assert_eq!(stats.map_errors, 1);
+                // identify, join, two maps, three queries
+                assert_eq!(stats.messages, 7);
+            }
+            reply => panic!("wrong reply {:?}", reply),
+        }
+        drop((client, reader));
+        server.shutdown().unwrap();
+    }
+}
+
+mod request {
+    use super::super::request::{ask, Query};
+    use crate::error::ErrorKind;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn timeout() {
+        let (coordinator, rx) = mpsc::channel();
+        let err = ask(
+            &coordinator,
+            "test",
+            Query::Stats,
+            Duration::from_millis(10),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::IoTimedOut);
+
+        // the request is still queued, a late reply is dropped
+        drop(rx.recv().unwrap());
+    }
+
+    #[test]
+    fn coordinator_stopped() {
+        let (coordinator, rx) = mpsc::channel();
+        drop(rx);
+        assert!(ask(&coordinator, "test", Query::Stats, Duration::from_secs(1)).is_err());
+    }
+}
+
+mod room {
+    use super::super::room::Room;
+    use super::super::transport::{pipe, Pipe, Writer};
+    use std::io::prelude::*;
+    use std::io::BufReader;
+
+    /// returns the writer of a member speaking protocol 0.2.0
+    fn member(room_side: Pipe) -> Writer {
+        let writer = Writer::new(Box::new(room_side));
+        writer.accept_notices();
+        writer
+    }
+
+    #[test]
+    fn events_for_members() {
+        let (a, a_room) = pipe("a");
+        let (b, b_room) = pipe("b");
+        let mut room = Room::spawn("hall", 0, None);
+        assert_eq!(room.name(), "hall");
+
+        room.join("a", Some(member(a_room)));
+        room.join("b", Some(member(b_room)));
+        room.leave("b");
+        assert_eq!(room.members(), ["a".to_string()]);
+        room.leave("a");
+        assert!(room.is_empty());
+        drop(room); // stops the thread after the queued messages
+
+        let mut events = String::new();
+        BufReader::new(a).read_to_string(&mut events).unwrap();
+        assert_eq!(events, "event join b hall\nevent leave b hall\n");
+        let mut events = String::new();
+        BufReader::new(b).read_to_string(&mut events).unwrap();
+        assert_eq!(events, "");
+    }
+
+    #[test]
+    fn started_again_after_the_last_events() {
+        let (a, a_room) = pipe("a");
+        let a_room = member(a_room);
+        let mut room = Room::spawn("hall", 0, None);
+        room.join("a", Some(a_room.clone()));
+        room.join("b", None);
+        room.leave("a");
+        let stopped = room.stop();
+        assert!(stopped.is_some());
+
+        let mut room = Room::spawn("hall", 0, stopped);
+        room.join("a", Some(a_room));
+        room.join("c", None);
+        drop(room);
+
+        let mut events = String::new();
+        BufReader::new(a).read_to_string(&mut events).unwrap();
+        assert_eq!(events, "event join b hall\nevent join c hall\n");
+    }
+
+    #[test]
+    fn no_events_for_older_clients() {
+        let (a, a_room) = pipe("a");
+        let mut room = Room::spawn("hall", 0, None);
+        room.join("a", Some(Writer::new(Box::new(a_room))));
+        room.join("b", None);
+        drop(room);
+
+        let mut events = String::new();
+        BufReader::new(a).read_to_string(&mut events).unwrap();
+        assert_eq!(events, "");
+    }
+}
+
+mod transport {
+    use super::super::transport::{pipe, Transport};
+    use std::io::prelude::*;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    #[test]
+    fn pipe_both_directions() {
+        let (mut client, mut server) = pipe("both");
+        assert_eq!(server.peer(), "pipe:both");
+        client.write_all(b"ping\n").unwrap();
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping\n");
+
+        // clones share the connection
+        let mut writer = server.try_clone().unwrap();
+        writer.write_all(b"pong\n").unwrap();
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong\n");
+    }
+
+    #[test]
+    fn pipe_close() {
+        let (mut client, server) = pipe("close");
+        client.write_all(b"left").unwrap();
+        drop(client);
+        // bytes written before closing are still read
+        let mut read = String::new();
+        server
+            .try_clone()
+            .unwrap()
+            .read_to_string(&mut read)
+            .unwrap();
+        assert_eq!(read, "left");
+
+        let (mut client, server) = pipe("shutdown");
+        server.shutdown().unwrap();
+        assert_eq!(client.read(&mut [0; 4]).unwrap(), 0);
+        assert_eq!(
+            client.write(b"late").unwrap_err().kind(),
+            ErrorKind::BrokenPipe
+        );
+    }
+
+    #[test]
+    fn pipe_timeout() {
+        let (_client, mut server) = pipe("timeout");
+        server
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        assert_eq!(
+            server.read(&mut [0; 4]).unwrap_err().kind(),
+            ErrorKind::TimedOut
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use super::listener::Stream;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Transport is a connection a session runs over
+///
+/// A session reads the commands of the client and writes the answers, the
+/// coordinator writes notices from another thread and the server closes the
+/// connection on shutdown, so a transport has to hand out more handles to
+/// the same connection with `try_clone`.
+///
+/// The server brings transports for tcp and unix sockets (`Stream`), stdin
+/// and stdout (`Stdio`) and in-memory connections (`pipe`). Other connections,
+/// like a tls session, are served by implementing this trait for them and
+/// handing them to `ServerHandle::serve`.
+pub trait Transport: Read + Write + Send {
+    /// returns a second handle to the same connection
+    fn try_clone(&self) -> io::Result<Connection>;
+
+    /// returns a description of the client for the log
+    fn peer(&self) -> String;
+
+    /// closes the connection, so reads blocked on other handles return
+    fn shutdown(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// sets the time to wait for data, `None` waits forever
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// sets the time to wait while writing, `None` waits forever
+    fn set_write_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// connection of one client, as the sessions use it
+pub type Connection = Box<dyn Transport>;
+
+impl fmt::Debug for dyn Transport {
+    /// shows the peer of the connection
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Transport({})", self.peer())
+    }
+}
+
+impl Transport for Stream {
+    fn try_clone(&self) -> io::Result<Connection> {
+        Ok(Box::new(Stream::try_clone(self)?))
+    }
+
+    fn peer(&self) -> String {
+        Stream::peer(self)
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        Stream::shutdown(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        Stream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        Stream::set_write_timeout(self, timeout)
+    }
+}
+
+/// Writer is the one handle every line to a client is written through
+///
+/// The session answers commands, the rooms send events and the server sends
+/// the shutdown notice, each from its own thread. Every line is written whole
+/// while holding a lock, so a short line never ends up inside a long one.
+///
+/// # Example
+/// ```
+/// use poke_escape_server::server::transport::{pipe, Writer};
+/// use std::io::prelude::*;
+/// let (mut client, server) = pipe("writer");
+/// let writer = Writer::new(Box::new(server));
+/// writer.clone().write_line("event join ash hall").unwrap();
+/// drop(writer);
+/// let mut read = String::new();
+/// client.read_to_string(&mut read).unwrap();
+/// assert_eq!(read, "event join ash hall\n");
+/// ```
+#[derive(Clone)]
+pub struct Writer {
+    /// connection the lines are written to
+    p_stream: Arc<Mutex<Connection>>,
+
+    /// set once the client speaks the line protocol, see `notice`
+    p_notices: Arc<AtomicBool>,
+}
+
+impl Writer {
+    /// creates the writer of a connection
+    ///
+    /// Use a handle of the connection nobody else writes to.
+    pub fn new(stream: Connection) -> Self {
+        Writer {
+            p_stream: Arc::new(Mutex::new(stream)),
+            p_notices: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// writes a line, the line break is added
+    pub fn write_line(&self, line: &str) -> io::Result<()> {
+        self.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    /// writes all bytes before any other write of the connection
+    pub fn write_all(&self, buf: &[u8]) -> io::Result<()> {
+        let mut stream = self.p_stream.lock().unwrap_or_else(|p| p.into_inner());
+        stream.write_all(buf)?;
+        stream.flush()
+    }
+
+    /// lets `notice` write to the client
+    ///
+    /// Called after the handshake of clients speaking protocol 0.2.0 or newer,
+    /// see `server::PROTOCOL_VERSION`. Other clients would take a notice for
+    /// the answer to a command.
+    pub fn accept_notices(&self) {
+        self.p_notices.store(true, Ordering::SeqCst);
+    }
+
+    /// writes a line the client did not ask for, like the shutdown notice
+    ///
+    /// Returns false without writing if the client does not accept notices,
+    /// like http clients, older clients and clients still in the handshake.
+    pub fn notice(&self, line: &str) -> io::Result<bool> {
+        if !self.p_notices.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        self.write_line(line)?;
+        Ok(true)
+    }
+}
+
+impl fmt::Debug for Writer {
+    /// shows the peer of the connection
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.p_stream.try_lock() {
+            Ok(stream) => write!(f, "Writer({})", stream.peer()),
+            Err(_) => write!(f, "Writer(busy)"),
+        }
+    }
+}
+
+/// Stdio reads from stdin and writes to stdout
+///
+/// Used to serve one client started by inetd or a similar launcher, see
+/// `Config::serve_stdio`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stdio;
+
+impl Read for Stdio {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::stdin().read(buf)
+    }
+}
+
+impl Write for Stdio {
+    /// writes to stdout, flushed at once so the client sees every line
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut stdout = io::stdout();
+        let written = stdout.write(buf)?;
+        stdout.flush()?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+impl Transport for Stdio {
+    fn try_clone(&self) -> io::Result<Connection> {
+        Ok(Box::new(Stdio))
+    }
+
+    fn peer(&self) -> String {
+        "stdio".to_string()
+    }
+}
+
+/// bytes sent in one direction of a `Pipe`
+#[derive(Default)]
+struct Channel {
+    /// bytes written and not read yet, and true after the channel was closed
+    p_state: Mutex<(VecDeque<u8>, bool)>,
+
+    /// signaled when bytes are written or the channel is closed
+    p_changed: Condvar,
+}
+
+impl Channel {
+    fn lock(&self) -> MutexGuard<'_, (VecDeque<u8>, bool)> {
+        self.p_state.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    fn close(&self) {
+        self.lock().1 = true;
+        self.p_changed.notify_all();
+    }
+}
+
+/// one end of a pipe, closes both directions when the last handle is dropped
+struct End {
+    /// bytes read by this end
+    p_incoming: Arc<Channel>,
+
+    /// bytes written by this end
+    p_outgoing: Arc<Channel>,
+}
+
+impl Drop for End {
+    fn drop(&mut self) {
+        self.p_incoming.close();
+        self.p_outgoing.close();
+    }
+}
+
+/// Pipe is one end of an in-memory connection, created with `pipe`
+///
+/// Reads block until the other end wrote, or return 0 after one end was
+/// closed. Used to run sessions in tests without sockets.
+#[derive(Clone)]
+pub struct Pipe {
+    /// shared by every handle of this end
+    p_end: Arc<End>,
+
+    /// name used as peer
+    p_name: String,
+
+    /// time to wait for data, `None` waits forever
+    p_timeout: Arc<Mutex<Option<Duration>>>,
+}
+
+/// creates an in-memory connection, returns both ends
+///
+/// # Example
+/// ```
+/// use poke_escape_server::server::transport::pipe;
+/// use std::io::prelude::*;
+/// let (mut client, mut server) = pipe("test");
+/// client.write_all(b"quit\n").unwrap();
+/// let mut buf = [0; 5];
+/// server.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf, b"quit\n");
+/// ```
+pub fn pipe(name: &str) -> (Pipe, Pipe) {
+    let to_server = Arc::new(Channel::default());
+    let to_client = Arc::new(Channel::default());
+    let end = |incoming: &Arc<Channel>, outgoing: &Arc<Channel>, name: String| Pipe {
+        p_end: Arc::new(End {
+            p_incoming: Arc::clone(incoming),
+            p_outgoing: Arc::clone(outgoing),
+        }),
+        p_name: name,
+        p_timeout: Arc::new(Mutex::new(None)),
+    };
+    (
+        end(&to_client, &to_server, format!("pipe:{}:client", name)),
+        end(&to_server, &to_client, format!("pipe:{}", name)),
+    )
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = *self.p_timeout.lock().unwrap_or_else(|p| p.into_inner());
+        let channel = &self.p_end.p_incoming;
+        let mut state = channel.lock();
+        while state.0.is_empty() && !state.1 {
+            state = match timeout {
+                Some(timeout) => {
+                    let (state, result) = channel
+                        .p_changed
+                        .wait_timeout(state, timeout)
+                        .unwrap_or_else(|p| p.into_inner());
+                    if result.timed_out() && state.0.is_empty() && !state.1 {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                    state
+                }
+                None => channel
+                    .p_changed
+                    .wait(state)
+                    .unwrap_or_else(|p| p.into_inner()),
+            };
+        }
+        let n = buf.len().min(state.0.len());
+        for (byte, read) in buf.iter_mut().zip(state.0.drain(..n)) {
+            *byte = read;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for Pipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let channel = &self.p_end.p_outgoing;
+        let mut state = channel.lock();
+        if state.1 {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        state.0.extend(buf);
+        channel.p_changed.notify_all();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Pipe {
+    fn try_clone(&self) -> io::Result<Connection> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn peer(&self) -> String {
+        self.p_name.clone()
+    }
+
+    fn shutdown(&self) -> io::Result<()> {
+        self.p_end.p_incoming.close();
+        self.p_end.p_outgoing.close();
+        Ok(())
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        *self.p_timeout.lock().unwrap_or_else(|p| p.into_inner()) = timeout;
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use serde_derive::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::error::{Error, ErrorKind};
+use super::server::listener::ListenAddr;
+
+#[doc(inline)]
+pub use super::error::Result;
+
+// tests as sub module
+#[cfg(test)] // only add when running tests
+mod test;
+
+/// prefix of the environment variables read by `ServerSettings::from_env`
+pub const ENV_PREFIX: &str = "POKE_ESCAPE_";
+
+/// keys of the `[Server]` section
+const SERVER_KEYS: &[&str] = &[
+    "host",
+    "port",
+    "listen",
+    "threads",
+    "read_timeout",
+    "write_timeout",
+    "max_connections",
+    "shutdown_timeout",
+    "watch",
+    "log",
+];
+
+/// keys of the `[Server.log]` section
+const LOG_KEYS: &[&str] = &["verbosity", "color"];
+
+/// ServerSettings holds the `[Server]` section of config.toml
+///
+/// Every key is optional, keys not set keep the value of the source with a
+/// lower precedence. `Config` applies the sources in the order defaults,
+/// config file, environment variables, cli flags. Unknown keys and unknown
+/// environment variables are not an error, they are listed in `ignored`.
+/// ```toml
+/// [Server]
+/// host = "127.0.0.1"
+/// port = 1996
+/// listen = ["0.0.0.0:1996", "[::]:1996", "unix:/run/poke_escape.sock"]
+/// threads = 8
+/// read_timeout = 30   # seconds, 0 disables the timeout
+/// write_timeout = 30  # seconds, 0 disables the timeout
+/// max_connections = 64  # 0 for no limit
+/// shutdown_timeout = 10  # seconds
+/// watch = false
+///
+/// [Server.log]
+/// verbosity = 1
+/// color = true
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    /// interface (ip) to listen on
+    pub host: Option<String>,
+
+    /// port to listen on
+    pub port: Option<u16>,
+
+    /// addresses to listen on, replaces `host` and `port`
+    ///
+    /// See `ListenAddr` for the format.
+    pub listen: Option<Vec<ListenAddr>>,
+
+    /// number of threads in the ThreadPool
+    pub threads: Option<usize>,
+
+    /// seconds to wait for data from a client, 0 disables the timeout
+    pub read_timeout: Option<u64>,
+
+    /// seconds to wait while writing to a client, 0 disables the timeout
+    pub write_timeout: Option<u64>,
+
+    /// number of clients served at the same time, 0 for no limit
+    pub max_connections: Option<usize>,
+
+    /// seconds the clients get to leave when the server stops
+    pub shutdown_timeout: Option<u64>,
+
+    /// reload the config when it changes
+    pub watch: Option<bool>,
+
+    /// output of the server
+    pub log: LogSettings,
+
+    /// unknown keys and environment variables, which were ignored
+    #[serde(skip)]
+    pub ignored: Vec<String>,
+}
+
+/// LogSettings holds the `[Server.log]` section of config.toml
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// verbosity level, 0 is quiet
+    pub verbosity: Option<u8>,
+
+    /// force colored output on or off, detected from the terminal if not set
+    pub color: Option<bool>,
+}
+
+impl ServerSettings {
+    /// reads the `[Server]` section of a config file
+    ///
+    /// A file without the section, or a file which does not exist, returns
+    /// empty settings. A missing file is reported by the map loader.
+    pub fn from_file(file: &str) -> Result<Self> {
+        if !Path::new(file).exists() {
+            return Ok(Self::default());
+        }
+        Self::from_str(&fs::read_to_string(file)?)
+    }
+
+    /// reads the settings from the `POKE_ESCAPE_*` environment variables
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(std::env::vars())
+    }
+
+    /// reads the settings from a list of environment variables
+    ///
+    /// The name of a variable is the prefix `POKE_ESCAPE_` followed by the key
+    /// in upper case, `POKE_ESCAPE_LOG_VERBOSITY` sets `log.verbosity`.
+    /// `POKE_ESCAPE_LISTEN` takes a comma separated list.
+    /// Variables not starting with the prefix are ignored, unknown variables
+    /// with the prefix are added to `ignored`.
+    ///
+    /// # Example
+    /// ```
+    /// use poke_escape_server::settings::ServerSettings;
+    /// let vars = vec![("POKE_ESCAPE_PORT".to_string(), "2019".to_string())];
+    /// let settings = ServerSettings::from_vars(vars).unwrap();
+    /// assert_eq!(settings.port, Some(2019));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `NotParsable` if a value has the wrong type.
+    pub fn from_vars<I: IntoIterator<Item = (String, String)>>(vars: I) -> Result<Self> {
+        let mut settings = Self::default();
+        for (name, value) in vars {
+            let key = match name.strip_prefix(ENV_PREFIX) {
+                Some(key) => key,
+                None => continue,
+            };
+            match key {
+                "HOST" => settings.host = Some(value),
+                "PORT" => settings.port = Some(parse(&name, &value)?),
+                "LISTEN" => {
+                    let mut listen = Vec::new();
+                    for addr in value.split(',').filter(|a| !a.trim().is_empty()) {
+                        listen.push(parse(&name, addr)?);
+                    }
+                    settings.listen = Some(listen);
+                }
+                "THREADS" => settings.threads = Some(parse(&name, &value)?),
+                "READ_TIMEOUT" => settings.read_timeout = Some(parse(&name, &value)?),
+                "WRITE_TIMEOUT" => settings.write_timeout = Some(parse(&name, &value)?),
+                "MAX_CONNECTIONS" => settings.max_connections = Some(parse(&name, &value)?),
+                "SHUTDOWN_TIMEOUT" => settings.shutdown_timeout = Some(parse(&name, &value)?),
+                "WATCH" => settings.watch = Some(parse(&name, &value)?),
+                "LOG_VERBOSITY" => settings.log.verbosity = Some(parse(&name, &value)?),
+                "LOG_COLOR" => settings.log.color = Some(parse(&name, &value)?),
+                // read by main to find the config file
+                "CONFIG" => (),
+                // a stray variable should not keep the server from starting
+                _ => settings.ignored.push(name),
+            }
+        }
+        Ok(settings)
+    }
+}
+
+impl FromStr for ServerSettings {
+    type Err = Error;
+
+    /// reads the `[Server]` section of the content of a config file
+    ///
+    /// Unknown keys are added to `ignored` as `Server.<key>`.
+    fn from_str(content: &str) -> Result<Self> {
+        let content: toml::Value = toml::from_str(content)?;
+        let mut server = match content.get("Server") {
+            Some(server) => server.clone(),
+            None => return Ok(Self::default()),
+        };
+        let mut ignored = Vec::new();
+        if let Some(table) = server.as_table_mut() {
+            ignore_unknown(table, SERVER_KEYS, "Server", &mut ignored);
+            if let Some(log) = table.get_mut("log").and_then(|log| log.as_table_mut()) {
+                ignore_unknown(log, LOG_KEYS, "Server.log", &mut ignored);
+            }
+        }
+        let mut settings: Self = server.try_into()?;
+        settings.ignored = ignored;
+        Ok(settings)
+    }
+}
+
+/// removes the keys not in `known` from a section, adding them to `ignored`
+fn ignore_unknown(
+    table: &mut toml::value::Table,
+    known: &[&str],
+    section: &str,
+    ignored: &mut Vec<String>,
+) {
+    let unknown: Vec<String> = table
+        .keys()
+        .filter(|key| !known.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    for key in unknown {
+        table.remove(&key);
+        ignored.push(format!("{}.{}", section, key));
+    }
+}
+
+/// parses the value of an environment variable
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T> {
+    value.trim().parse().map_err(|_| {
+        Error::new(ErrorKind::NotParsable(format!(
+            "{}: invalid value {}",
+            name, value
+        )))
+    })
+}
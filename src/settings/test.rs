@@ -0,0 +1,164 @@
+//! test file for the server settings
+
+mod server_settings {
+    use super::super::{LogSettings, ServerSettings};
+    use crate::error::ErrorKind;
+    use crate::Config;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn from_file() {
+        let settings = ServerSettings::from_str(
+            "[Maps]\nversion = \"0.1.0\"\n\n[Server]\nport = 2019\nread_timeout = 0\n\n[Server.log]\nverbosity = 2\n",
+        )
+        .unwrap();
+        assert_eq!(
+            settings,
+            ServerSettings {
+                port: Some(2019),
+                read_timeout: Some(0),
+                log: LogSettings {
+                    verbosity: Some(2),
+                    color: None,
+                },
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn without_section() {
+        let settings = ServerSettings::from_str("[Maps]\nversion = \"0.1.0\"\n").unwrap();
+        assert_eq!(settings, ServerSettings::default());
+        let settings = ServerSettings::from_file("/does/not/exist.toml").unwrap();
+        assert_eq!(settings, ServerSettings::default());
+    }
+
+    #[test]
+    fn unknown_key() {
+        let settings = ServerSettings::from_str(
+            "[Server]\nprot = 2019\nthreads = 2\n[Server.log]\nlevel = 1\n",
+        )
+        .unwrap();
+        assert_eq!(settings.threads, Some(2));
+        assert_eq!(settings.ignored, vec!["Server.prot", "Server.log.level"]);
+        assert!(ServerSettings::from_str("[Server]\nport = \"many\"\n").is_err());
+
+        // every key of the documented section is known
+        let documented = include_str!("mod.rs")
+            .lines()
+            .skip_while(|line| !line.starts_with("/// [Server]"))
+            .take_while(|line| !line.starts_with("/// ```"))
+            .map(|line| line.trim_start_matches("///"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let settings = ServerSettings::from_str(&documented).unwrap();
+        assert!(settings.ignored.is_empty(), "{:?}", settings.ignored);
+        assert_eq!(settings.log.verbosity, Some(1));
+    }
+
+    #[test]
+    fn from_vars() {
+        let settings = ServerSettings::from_vars(vars(&[
+            ("POKE_ESCAPE_HOST", "::1"),
+            ("POKE_ESCAPE_WATCH", "true"),
+            ("POKE_ESCAPE_LOG_COLOR", "false"),
+            ("POKE_ESCAPE_CONFIG", "other.toml"),
+            ("PATH", "/bin"),
+        ]))
+        .unwrap();
+        assert_eq!(settings.host, Some("::1".to_string()));
+        assert_eq!(settings.watch, Some(true));
+        assert_eq!(settings.log.color, Some(false));
+        assert_eq!(settings.port, None);
+
+        let err = ServerSettings::from_vars(vars(&[("POKE_ESCAPE_PORT", "high")])).unwrap_err();
+        assert!(
+            matches!(err.kind(), ErrorKind::NotParsable(reason) if reason.contains("POKE_ESCAPE_PORT"))
+        );
+        let settings = ServerSettings::from_vars(vars(&[
+            ("POKE_ESCAPE_PROT", "1"),
+            ("POKE_ESCAPE_THREADS", "3"),
+        ]))
+        .unwrap();
+        assert_eq!(settings.threads, Some(3));
+        assert_eq!(settings.port, None);
+        assert_eq!(settings.ignored, vec!["POKE_ESCAPE_PROT"]);
+    }
+
+    #[test]
+    fn precedence() {
+        let file = ServerSettings::from_str(
+            "[Server]\nhost = \"0.0.0.0\"\nport = 2019\nthreads = 2\nwrite_timeout = 5\n",
+        )
+        .unwrap();
+        let env = ServerSettings::from_vars(vars(&[
+            ("POKE_ESCAPE_PORT", "2020"),
+            ("POKE_ESCAPE_THREADS", "4"),
+        ]))
+        .unwrap();
+        let cli = ServerSettings {
+            threads: Some(6),
+            ..Default::default()
+        };
+
+        let mut config = Config::new();
+        config.apply(&file);
+        config.apply(&env);
+        config.apply(&cli);
+        assert_eq!(config.host, "0.0.0.0");
+        assert_eq!(config.port, 2020);
+        assert_eq!(config.threads, 6);
+        assert_eq!(config.write_timeout, Some(Duration::from_secs(5)));
+        // not set anywhere, default stays
+        assert_eq!(config.read_timeout, None);
+        assert!(!config.verbose);
+    }
+
+    #[test]
+    fn listen() {
+        use crate::server::listener::ListenAddr;
+        let file = ServerSettings::from_str(
+            "[Server]\nlisten = [\"[::]:2019\", \"unix:/tmp/poke.sock\"]\n",
+        )
+        .unwrap();
+        let mut config = Config::new();
+        assert_eq!(
+            config.listen_addrs(),
+            vec![ListenAddr::Tcp("127.0.0.1:1996".to_string())]
+        );
+        config.apply(&file);
+        assert_eq!(
+            config.listen_addrs(),
+            vec![
+                ListenAddr::Tcp("[::]:2019".to_string()),
+                ListenAddr::Unix("/tmp/poke.sock".into())
+            ]
+        );
+
+        // a later port replaces the list
+        let env = ServerSettings::from_vars(vars(&[("POKE_ESCAPE_PORT", "2020")])).unwrap();
+        config.apply(&env);
+        assert_eq!(
+            config.listen_addrs(),
+            vec![ListenAddr::Tcp("127.0.0.1:2020".to_string())]
+        );
+        config.host = "::1".to_string();
+        assert_eq!(
+            config.listen_addrs(),
+            vec![ListenAddr::Tcp("[::1]:2020".to_string())]
+        );
+
+        assert!(ServerSettings::from_str("[Server]\nlisten = [\"nowhere\"]\n").is_err());
+        let env =
+            ServerSettings::from_vars(vars(&[("POKE_ESCAPE_LISTEN", "[::1]:1, unix:/a")])).unwrap();
+        assert_eq!(env.listen.map(|l| l.len()), Some(2));
+    }
+}
@@ -0,0 +1,74 @@
+use super::server::listener::ListenAddr;
+use super::Config;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// number of directories created by this process, keeps the names unique
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// TempDir is a directory for one test, removed with its content on drop
+#[derive(Debug)]
+pub struct TempDir {
+    /// path of the directory
+    p_path: PathBuf,
+}
+
+impl TempDir {
+    /// creates an empty directory in the temporary directory of the system
+    ///
+    /// `name` shows up in the path, the path is unique also for equal names.
+    pub fn new(name: &str) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "poke_escape_{}_{}_{}",
+            name,
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&path); // left over by a killed run
+        fs::create_dir_all(&path)?;
+        Ok(TempDir { p_path: path })
+    }
+
+    /// returns the path of a file in the directory
+    pub fn join<P: AsRef<Path>>(&self, file: P) -> PathBuf {
+        self.p_path.join(file)
+    }
+
+    /// writes a file into the directory, creating the directories in between
+    ///
+    /// Returns the path of the file.
+    pub fn write<P: AsRef<Path>>(&self, file: P, content: &str) -> io::Result<PathBuf> {
+        let path = self.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.p_path);
+    }
+}
+
+/// returns a config serving `random` maps on an ephemeral port
+///
+/// The config file is written into `dir`, which has to live as long as the
+/// server.
+pub fn config(dir: &TempDir) -> io::Result<Config> {
+    let file = dir.write(
+        "config.toml",
+        "[Maps]\nversion = \"0.2.0\"\n\n[map.random]\nversion = \"0.1.0\"\nformat = \"random\"\n",
+    )?;
+    let mut config = Config::new();
+    config.config = file.to_string_lossy().to_string();
+    config.listen = vec![ListenAddr::Tcp("127.0.0.1:0".to_string())];
+    config.threads = 4;
+    config.shutdown_timeout = Duration::from_secs(5);
+    Ok(config)
+}
@@ -2,7 +2,7 @@ use std::fmt;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
-/// threads is the lib for the ThreadPool struct
+// threads is the lib for the ThreadPool struct
 
 #[doc(inline)]
 pub use super::error::Result;
@@ -128,7 +128,7 @@ impl ThreadPool {
     }
 
     /// execute send a function into a thread to be executed there
-    pub fn execute<'a, F>(&self, f: F) -> Result<()>
+    pub fn execute<F>(&self, f: F) -> Result<()>
     where
         F: FnOnce() + Send + 'static,
     {
@@ -221,7 +221,7 @@ trait FnBox {
 }
 
 /// Type for the Job to send to a worker
-type Job = Box<FnBox + Send + 'static>;
+type Job = Box<dyn FnBox + Send + 'static>;
 
 /// implementation of FnBox for the job type
 impl<F: FnOnce()> FnBox for F {
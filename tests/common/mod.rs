@@ -0,0 +1,254 @@
+//! harness starting a server for the integration tests
+//!
+//! `TestServer` runs a server on an ephemeral port with a temporary config
+//! and maps directory, `Session` plays scripted transcripts against it.
+#![allow(dead_code)] // not every test uses every helper
+
+use poke_escape_server::map::MapPlaces;
+use poke_escape_server::server::handle::ServerHandle;
+use poke_escape_server::server::listener::ListenAddr;
+use poke_escape_server::Config;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// time to wait for a line of the server before a test fails
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// version the server sends in the handshake
+pub const SERVER_HELLO: &str = concat!("POKE-ESCAPE-SERVER_", env!("CARGO_PKG_VERSION"));
+
+/// map file put into the maps directory, one row of open cells from the
+/// start to a water cell, walled in on every side
+pub const TINY_MAP: &str = r#"{
+    "name": "Tiny",
+    "features": ["none"],
+    "map": [
+        [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+        [1, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1],
+        [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]
+    ]
+}
+"#;
+
+/// number of directories created by this process, keeps the names unique
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// TempDir is a directory for one test, removed with its content on drop
+#[derive(Debug)]
+pub struct TempDir {
+    /// path of the directory
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// creates an empty directory in the temporary directory of the system
+    ///
+    /// `name` shows up in the path, the path is unique also for equal names.
+    pub fn new(name: &str) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "poke_escape_{}_{}_{}",
+            name,
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&path); // left over by a killed run
+        fs::create_dir_all(&path)?;
+        Ok(TempDir { path })
+    }
+
+    /// returns the path of a file in the directory
+    pub fn join<P: AsRef<Path>>(&self, file: P) -> PathBuf {
+        self.path.join(file)
+    }
+
+    /// writes a file into the directory, creating the directories in between
+    ///
+    /// Returns the path of the file.
+    pub fn write<P: AsRef<Path>>(&self, file: P, content: &str) -> io::Result<PathBuf> {
+        let path = self.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// returns a config serving `random` maps on an ephemeral port
+///
+/// `maps` are more lines for the `[Maps]` section. The config file is
+/// written into `dir`, which has to live as long as the server.
+pub fn config_with(dir: &TempDir, maps: &str) -> io::Result<Config> {
+    let file = dir.write(
+        "config.toml",
+        &format!(
+            "[Maps]\nversion = \"0.2.0\"\n{}\n[map.random]\nversion = \"0.1.0\"\nformat = \"random\"\n",
+            maps
+        ),
+    )?;
+    let mut config = Config::new();
+    config.config = file.to_string_lossy().to_string();
+    config.listen = vec![ListenAddr::Tcp("127.0.0.1:0".to_string())];
+    config.threads = 4;
+    config.shutdown_timeout = Duration::from_secs(5);
+    Ok(config)
+}
+
+/// TestServer is a server running for one test
+///
+/// The server is stopped and the temporary directory removed on drop.
+pub struct TestServer {
+    /// directory holding config.toml and the maps directory
+    pub dir: TempDir,
+
+    /// config the server was started with
+    pub config: Config,
+
+    /// address the server listens on
+    pub addr: String,
+
+    /// the running server
+    handle: Option<ServerHandle>,
+}
+
+impl TestServer {
+    /// starts a server serving `Tiny` from the maps directory and `random`
+    ///
+    /// `name` has to be unique per test, it names the temporary directory.
+    pub fn start(name: &str) -> Self {
+        let dir = TempDir::new(&format!("it_{}", name)).unwrap();
+        dir.write("maps/tiny.map", TINY_MAP).unwrap();
+        let maps = format!("directory = {:?}\n", dir.join("maps").to_string_lossy());
+        let mut config = config_with(&dir, &maps).unwrap();
+        config.shutdown_timeout = Duration::from_secs(1);
+        let handle = config.start().unwrap();
+        let addr = handle.local_addrs()[0].to_string();
+        TestServer {
+            dir,
+            config,
+            addr,
+            handle: Some(handle),
+        }
+    }
+
+    /// connects a new client, without sending anything
+    pub fn connect(&self) -> Session {
+        let stream = TcpStream::connect(&self.addr).unwrap();
+        stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+        Session {
+            reader: BufReader::new(stream.try_clone().unwrap()),
+            stream,
+        }
+    }
+
+    /// connects a new client and finishes the handshake of protocol 0.2.0
+    pub fn session(&self) -> Session {
+        self.session_with("0.2.0")
+    }
+
+    /// connects a new client and finishes the handshake of a protocol version
+    pub fn session_with(&self, version: &str) -> Session {
+        let mut session = self.connect();
+        session.play(&format!("> POKE-ESCAPE_{}\n< {}\n", version, SERVER_HELLO));
+        session
+    }
+
+    /// returns the answer the server sends for `map <name>`
+    ///
+    /// The map is loaded from the same config, so the line is exact.
+    pub fn map_line(&self, name: &str) -> String {
+        let maps = MapPlaces::new(&self.config.config, false).unwrap();
+        format!("map {}", maps.get(name).unwrap())
+    }
+
+    /// stops the server, the clients get the shutdown notice
+    pub fn shutdown(mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.shutdown().unwrap();
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.shutdown();
+        }
+        // the directory is removed when the fields are dropped
+    }
+}
+
+/// Session is the connection of one scripted client
+pub struct Session {
+    /// connection commands are written to
+    stream: TcpStream,
+
+    /// connection answers are read from
+    reader: BufReader<TcpStream>,
+}
+
+impl Session {
+    /// plays a transcript, panics with both transcripts if the server differs
+    ///
+    /// Lines starting with `> ` are sent to the server, lines starting with
+    /// `< ` are the lines the server has to answer, in order. Other lines are
+    /// ignored.
+    pub fn play(&mut self, script: &str) {
+        let mut expected = String::new();
+        let mut actual = String::new();
+        for line in script.lines() {
+            if let Some(command) = line.strip_prefix("> ") {
+                self.send(command);
+                expected += &format!("{}\n", line);
+                actual += &format!("{}\n", line);
+            } else if let Some(answer) = line.strip_prefix("< ") {
+                expected += &format!("{}\n", line);
+                match self.read_line() {
+                    Some(read) => actual += &format!("< {}\n", read),
+                    None => {
+                        actual += "<closed>\n";
+                        break;
+                    }
+                }
+                if !actual.ends_with(&format!("< {}\n", answer)) {
+                    break;
+                }
+            }
+        }
+        assert_eq!(actual, expected, "transcript differs");
+    }
+
+    /// writes a line to the server
+    pub fn send(&mut self, line: &str) {
+        self.stream
+            .write_all(format!("{}\n", line).as_bytes())
+            .unwrap();
+    }
+
+    /// reads a line without the line break, `None` if the server closed
+    pub fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(line.trim_end().to_string()),
+            Err(err) => panic!("no answer of the server: {}", err),
+        }
+    }
+
+    /// asserts the server closed the connection without sending more
+    pub fn assert_closed(&mut self) {
+        assert_eq!(self.read_line(), None, "server sent more lines");
+    }
+}
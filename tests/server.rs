@@ -0,0 +1,251 @@
+//! end-to-end tests of the protocol, run against a real server
+mod common;
+
+use common::{TestServer, SERVER_HELLO};
+
+#[test]
+fn handshake_and_quit() {
+    let server = TestServer::start("handshake");
+    let mut session = server.connect();
+    session.play(&format!(
+        "> POKE-ESCAPE_0.1.0\n\
+         < {}\n\
+         > quit\n\
+         < Bye\n",
+        SERVER_HELLO
+    ));
+    session.assert_closed();
+}
+
+#[test]
+fn older_client_is_accepted() {
+    let server = TestServer::start("older_client");
+    let mut session = server.connect();
+    session.play(&format!(
+        "> POKE-ESCAPE_0.0.1\n\
+         < {}\n\
+         > quit\n\
+         < Bye\n",
+        SERVER_HELLO
+    ));
+    session.assert_closed();
+}
+
+#[test]
+fn protocol_mismatch() {
+    let server = TestServer::start("mismatch");
+
+    // newer client
+    let mut session = server.connect();
+    session.play(&format!(
+        "> POKE-ESCAPE_0.3.0\n\
+         < {}\n\
+         < Protocol mismatch.\n",
+        SERVER_HELLO
+    ));
+    session.assert_closed();
+
+    // no handshake at all
+    let mut session = server.connect();
+    session.play("> hello\n< Protocol mismatch.\n");
+    session.assert_closed();
+}
+
+#[test]
+fn identify_once() {
+    let server = TestServer::start("identify");
+    let mut session = server.session();
+    session.play(
+        "> identify ash\n\
+         < OK\n\
+         > identify misty\n\
+         < Error\n\
+         > identify ash ketchum\n\
+         < Error\n\
+         > quit\n\
+         < Bye\n",
+    );
+    session.assert_closed();
+}
+
+#[test]
+fn unknown_command() {
+    let server = TestServer::start("unknown");
+    let mut session = server.session();
+    session.play(
+        "> fly\n\
+         < Unknown command\n\
+         > quit\n\
+         < Bye\n",
+    );
+    session.assert_closed();
+}
+
+#[test]
+fn maps() {
+    let server = TestServer::start("maps");
+    let tiny = server.map_line("Tiny");
+    let seeded = server.map_line("random 7");
+    let mut session = server.session();
+    session.play(&format!(
+        "> identify ash\n\
+         < OK\n\
+         > map Tiny\n\
+         < {}\n\
+         > map random 7\n\
+         < {}\n\
+         > map nope\n\
+         < error could not load map\n\
+         > quit\n\
+         < Bye\n",
+        tiny, seeded
+    ));
+    session.assert_closed();
+}
+
+#[test]
+fn map_before_identify() {
+    let server = TestServer::start("anonymous_map");
+    let tiny = server.map_line("Tiny");
+    let mut session = server.session();
+    session.play(&format!(
+        "> map Tiny\n\
+         < {}\n\
+         > quit\n\
+         < Bye\n",
+        tiny
+    ));
+    session.assert_closed();
+}
+
+#[test]
+fn join_and_leave_rooms() {
+    let server = TestServer::start("rooms");
+    let tiny = server.map_line("Tiny");
+    let mut ash = server.session();
+    let mut misty = server.session();
+    let mut brock = server.session();
+
+    ash.play("> join hall\n< Error\n");
+    ash.play("> identify ash\n< OK\n> join hall\n< OK\n");
+    misty.play("> identify misty\n< OK\n> join hall\n< OK\n");
+    ash.play("< event join misty hall\n");
+
+    // joining the same room again changes nothing
+    misty.play("> join hall\n< OK\n");
+
+    // switching rooms leaves the old room, names may contain spaces
+    brock.play("> identify brock\n< OK\n> join pewter gym\n< OK\n");
+    misty.play("> join pewter gym\n< OK\n");
+    ash.play("< event leave misty hall\n");
+    brock.play("< event join misty pewter gym\n");
+
+    // quitting leaves the room
+    misty.play("> quit\n< Bye\n");
+    misty.assert_closed();
+    brock.play("< event leave misty pewter gym\n");
+
+    // nothing else was sent in the meantime
+    ash.play(&format!("> map Tiny\n< {}\n> quit\n< Bye\n", tiny));
+    ash.assert_closed();
+    brock.play("> quit\n< Bye\n");
+    brock.assert_closed();
+}
+
+#[test]
+fn chat_in_rooms() {
+    let server = TestServer::start("chat");
+    let mut ash = server.session();
+    let mut misty = server.session();
+    let mut brock = server.session();
+
+    ash.play("> identify ash\n< OK\n> chat hello\n< Error\n> join hall\n< OK\n");
+    misty.play("> identify misty\n< OK\n> join hall\n< OK\n");
+    brock.play("> identify brock\n< OK\n> join gym\n< OK\n");
+    ash.play("< event join misty hall\n");
+
+    // only the other members of the room get the message
+    misty.play("> chat hi ash, how are you?\n< OK\n");
+    ash.play("< event chat misty hi ash, how are you?\n> chat fine\n< OK\n");
+    misty.play("< event chat ash fine\n");
+
+    // nothing else was sent in the meantime
+    for session in [&mut ash, &mut misty, &mut brock] {
+        session.play("> chat\n< Error\n");
+    }
+}
+
+#[test]
+fn shutdown_notice() {
+    let server = TestServer::start("shutdown");
+    let mut identified = server.session();
+    identified.play("> identify ash\n< OK\n");
+
+    let stopping = std::thread::spawn(move || server.shutdown());
+    identified.play("< shutdown 1\n> quit\n< Bye\n");
+    identified.assert_closed();
+    stopping.join().unwrap();
+}
+
+#[test]
+fn http_status() {
+    let server = TestServer::start("status");
+    let mut ash = server.session();
+    ash.play("> identify ash\n< OK\n> join hall\n< OK\n");
+
+    let mut http = server.connect();
+    http.send("GET /status/clients HTTP/1.1\r\nHost: localhost\r\n\r");
+    let mut response = Vec::new();
+    while let Some(line) = http.read_line() {
+        response.push(line);
+    }
+    assert_eq!(response[0], "HTTP/1.1 200 OK");
+    assert!(response.contains(&"Content-Type: application/json".to_string()));
+    assert_eq!(response.last().unwrap(), r#"[{"id":"ash","room":"hall"}]"#);
+}
+
+#[test]
+fn http_map_report() {
+    let server = TestServer::start("report");
+    let get = |path: &str| {
+        let mut http = server.connect();
+        http.send(&format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r", path));
+        let mut response = Vec::new();
+        while let Some(line) = http.read_line() {
+            response.push(line);
+        }
+        response
+    };
+
+    let found = get("/maps/Tiny/report");
+    assert_eq!(found[0], "HTTP/1.1 200 OK");
+    assert!(found.last().unwrap().starts_with(r#"{"p_source":"Tiny""#));
+
+    let missing = get("/maps/nope/report");
+    assert_eq!(missing[0], "HTTP/1.1 404 Not Found");
+    assert!(missing
+        .last()
+        .unwrap()
+        .contains(r#"{"MissingInConfig":"nope"}"#));
+}
+
+#[test]
+fn older_client_gets_no_events() {
+    let server = TestServer::start("no_events");
+    let tiny = server.map_line("Tiny");
+    let mut ash = server.session();
+    let mut old = server.session_with("0.1.0");
+    ash.play("> identify ash\n< OK\n> join hall\n< OK\n");
+    old.play(&format!(
+        "> identify old\n> join hall\n> map Tiny\n< {}\n",
+        tiny
+    ));
+    ash.play("< event join old hall\n");
+
+    // ash got the notice, so the old client would have got it before Bye
+    let stopping = std::thread::spawn(move || server.shutdown());
+    ash.play("< shutdown 1\n> quit\n< Bye\n");
+    old.play("> quit\n< Bye\n");
+    old.assert_closed();
+    stopping.join().unwrap();
+}